@@ -0,0 +1,266 @@
+//! Command line interface of the bot.
+
+use crate::CONFIG;
+use crate::commands;
+use clap::{Parser, Subcommand};
+use poise::serenity_prelude::Http;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::SystemTime;
+use utils::command_sync;
+use utils::config::{Config, config_path};
+use utils::logging::{ParseFilterError, TargetFilter, parse_time};
+use utils::shared_types::{Data, Error};
+
+/// Runs the discord bot
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Cli {
+    /// Path of the config file. Defaults to the `CONFIG_PATH` environment variable or
+    /// `config.toml`
+    #[arg(short, long, global = true, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+
+    /// Log levels like `debug` or `info,commands::purge=debug`, applied on top of the config file
+    /// and the `LOG_LEVELS` environment variable
+    #[arg(short, long, global = true, value_name = "LEVELS", value_parser = parse_levels)]
+    pub log_level: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+/// The subcommands of the [`Cli`].
+#[derive(Debug, Default, Subcommand)]
+pub enum Command {
+    /// Runs the bot (default)
+    #[default]
+    Run,
+    /// Loads the config file and reports any errors
+    CheckConfig,
+    /// Syncs the slash commands to the configured guilds without starting the bot
+    SyncCommands {
+        /// Only show what would change
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Lists all commands and their subcommands
+    ListCommands,
+    /// Deletes the files in the log directory except the active log file
+    ///
+    /// Do not run this while the bot is running, it may be writing or rotating the files.
+    ClearLogs {
+        /// Only delete files last modified before this time, e.g. `7d`, `12h` or `2024-05-01`
+        #[arg(long, value_name = "TIME")]
+        older_than: Option<String>,
+    },
+}
+
+impl Command {
+    /// Whether the command needs the global [`CONFIG`], which requires `DISCORD_TOKEN`.
+    pub fn uses_config(&self) -> bool {
+        !matches!(self, Self::CheckConfig | Self::ListCommands)
+    }
+}
+
+fn parse_levels(directives: &str) -> Result<String, ParseFilterError> {
+    directives.parse::<TargetFilter>()?;
+    Ok(directives.to_string())
+}
+
+/// Applies the `--log-level` directives to the logger.
+pub fn apply_log_levels(directives: Option<&str>) {
+    let Some(directives) = directives else {
+        return;
+    };
+
+    let mut levels = CONFIG.logger.levels();
+    // Already validated while parsing the arguments
+    if levels.apply(directives).is_ok() {
+        CONFIG.logger.set_levels(levels);
+    }
+}
+
+/// Prints the names and descriptions of all commands.
+pub fn list_commands() -> ExitCode {
+    for command in commands::all() {
+        print_command(&command, 0);
+    }
+    ExitCode::SUCCESS
+}
+
+fn print_command(command: &poise::Command<Data, Error>, depth: usize) {
+    let name = format!("{}{}", "  ".repeat(depth), command.name);
+    println!(
+        "{:<20} {}",
+        name,
+        command.description.as_deref().unwrap_or_default()
+    );
+    for subcommand in &command.subcommands {
+        print_command(subcommand, depth + 1);
+    }
+}
+
+/// Checks the config file and that the enabled commands of every guild exist. Works without the
+/// token.
+pub fn check_config() -> ExitCode {
+    let (path, required) = config_path();
+    let settings = match Config::check(&path, required) {
+        Ok(settings) => settings,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let names: Vec<String> = commands::all()
+        .into_iter()
+        .map(|command| command.name)
+        .collect();
+    let mut valid = true;
+    for (guild_id, guild) in &settings.guilds {
+        for name in guild.enabled_commands.iter().flatten() {
+            if !names.contains(name) {
+                eprintln!(
+                    "Unknown command '{}' in 'enabled_commands' of guild {}",
+                    name, guild_id
+                );
+                valid = false;
+            }
+        }
+    }
+    if !valid {
+        return ExitCode::FAILURE;
+    }
+
+    if path.exists() {
+        println!(
+            "'{}' is valid, {} guilds configured",
+            path.display(),
+            settings.guilds.len()
+        );
+    } else {
+        println!("'{}' does not exist, the defaults are used", path.display());
+    }
+    ExitCode::SUCCESS
+}
+
+/// Syncs the commands to every configured guild without connecting to the gateway.
+pub async fn sync_commands(dry_run: bool) -> ExitCode {
+    let http = Http::new(&CONFIG.token);
+    match http.get_current_application_info().await {
+        Ok(application) => http.set_application_id(application.id),
+        Err(e) => {
+            CONFIG
+                .logger
+                .error(&format!("Could not get the application: {}", e));
+            return ExitCode::FAILURE;
+        }
+    }
+
+    let mut code = ExitCode::SUCCESS;
+    match command_sync::delete_global_commands(&http, dry_run).await {
+        Ok(deleted) if !deleted.is_empty() => {
+            let verb = if dry_run { "Would delete" } else { "Deleted" };
+            println!("{} global commands: {}", verb, deleted.join(", "));
+        }
+        Ok(_) => {}
+        Err(e) => {
+            CONFIG
+                .logger
+                .error(&format!("Failed to delete global commands: {}", e));
+            code = ExitCode::FAILURE;
+        }
+    }
+
+    let commands = commands::all();
+    for (guild_id, guild) in &CONFIG.settings().guilds {
+        match command_sync::sync_commands(&http, *guild_id, &commands, guild, dry_run).await {
+            Ok(summary) => {
+                let verb = if dry_run { "Would sync" } else { "Synced" };
+                println!("{} commands in guild {}:", verb, guild_id);
+                print!("{}", summary);
+            }
+            Err(e) => {
+                CONFIG.logger.error(&format!(
+                    "Failed to sync commands in guild {}: {}",
+                    guild_id, e
+                ));
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+    code
+}
+
+/// Deletes the files in the log directory except the active log file, or only those last modified
+/// before `older_than`.
+pub fn clear_logs(older_than: Option<&str>) -> ExitCode {
+    let cutoff = match older_than {
+        Some(input) => match parse_time(input, CONFIG.logger.timezone()) {
+            Some(time) => Some(SystemTime::from(time)),
+            None => {
+                eprintln!(
+                    "Invalid time '{}', expected a duration like '7d' or '12h' or a date like \
+                     '2024-05-01'",
+                    input
+                );
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
+
+    let entries = match std::fs::read_dir(&CONFIG.log_dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            CONFIG
+                .logger
+                .error(&format!("Error reading log directory: {}", e));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let active = Path::new(&CONFIG.log_dir).join(&CONFIG.logging.file_name);
+    let mut deleted = 0;
+    let mut code = ExitCode::SUCCESS;
+    for entry in entries {
+        let entry = match entry {
+            Ok(e) => e,
+            Err(e) => {
+                CONFIG
+                    .logger
+                    .error(&format!("Error reading log directory: {}", e));
+                code = ExitCode::FAILURE;
+                continue;
+            }
+        };
+
+        let path = entry.path();
+        // The logger of this process already opened it
+        if !path.is_file() || path == active {
+            continue;
+        }
+        if let Some(cutoff) = cutoff {
+            let modified = entry.metadata().and_then(|metadata| metadata.modified());
+            if modified.is_ok_and(|modified| modified >= cutoff) {
+                continue;
+            }
+        }
+
+        match std::fs::remove_file(&path) {
+            Ok(()) => deleted += 1,
+            Err(e) => {
+                CONFIG.logger.error(&format!(
+                    "Error deleting log file {}: {}",
+                    path.display(),
+                    e
+                ));
+                code = ExitCode::FAILURE;
+            }
+        }
+    }
+
+    println!("Deleted {} log files", deleted);
+    code
+}
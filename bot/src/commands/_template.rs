@@ -3,7 +3,7 @@ use crate::CONFIG;
 use utils::shared_types::{Context, Error, CommandsExport};
 
 async fn check(ctx: Context<'_>) -> Result<bool, Error> {
-    Ok(utils::check_role(CONFIG.ß_roles.clone(), &ctx, &CONFIG.logger).await)
+    Ok(utils::check(|commands| &commands.ß, &ctx, &CONFIG).await)
 }
 
 #[poise::command(slash_command, guild_only, check = check)]
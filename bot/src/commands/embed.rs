@@ -10,7 +10,7 @@ use utils::shared_types::{CommandsExport, Context, Error};
 type EmbedField = (String, String, bool);
 
 async fn check(ctx: Context<'_>) -> Result<bool, Error> {
-    Ok(utils::check(CONFIG.commands.embed, &ctx, &CONFIG.logger).await)
+    Ok(utils::check(|commands| &commands.embed, &ctx, &CONFIG).await)
 }
 
 /// Send a message with an embed.
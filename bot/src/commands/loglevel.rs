@@ -0,0 +1,137 @@
+use crate::CONFIG;
+use poise::CreateReply;
+use utils::logging::LogLevel;
+use utils::shared_types::{CommandsExport, Context, Error};
+
+/// Target that changes the level of everything without its own level.
+const DEFAULT_TARGET: &str = "default";
+
+/// Target that changes the level of dependencies without their own level.
+const DEPENDENCIES_TARGET: &str = "dependencies";
+
+#[derive(Debug, Clone, Copy, poise::ChoiceParameter)]
+pub enum Level {
+    #[name = "Debug"]
+    Debug,
+    #[name = "Info"]
+    Info,
+    #[name = "Warn"]
+    Warn,
+    #[name = "Error"]
+    Error,
+    #[name = "Off"]
+    Off,
+}
+
+impl From<Level> for LogLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Debug => LogLevel::Debug,
+            Level::Info => LogLevel::Info,
+            Level::Warn => LogLevel::Warn,
+            Level::Error => LogLevel::Error,
+            Level::Off => LogLevel::Off,
+        }
+    }
+}
+
+/// Shows or changes the log levels.
+///
+/// Changes apply until the bot is restarted. To keep them, set `levels` in the config file.
+#[poise::command(
+    slash_command,
+    guild_only,
+    owners_only,
+    subcommands("show", "set", "reset"),
+    subcommand_required
+)]
+pub async fn loglevel(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows the current log levels.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn show(ctx: Context<'_>) -> Result<(), Error> {
+    let levels = CONFIG.logger.levels();
+
+    let mut content = format!(
+        "**Default:** `{}`\n**Dependencies:** `{}`",
+        levels.default_level().name(),
+        CONFIG.logger.dependency_level().name()
+    );
+    for (target, level) in levels.directives() {
+        content.push_str(&format!("\n`{}`: `{}`", target, level.name()));
+    }
+
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Sets the log level of a target.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn set(
+    ctx: Context<'_>,
+    #[description = "Module path like `commands::purge` or `serenity::gateway`, `default` or \
+                     `dependencies`"]
+    #[max_length = 100]
+    target: String,
+    #[description = "The minimum level of logged messages"] level: Level,
+) -> Result<(), Error> {
+    let target = target.trim();
+    let level = LogLevel::from(level);
+
+    match target {
+        DEFAULT_TARGET => CONFIG.logger.set_log_level(level.clone()),
+        DEPENDENCIES_TARGET => CONFIG.logger.set_dependency_level(level.clone()),
+        _ => CONFIG.logger.set_target_level(target, level.clone()),
+    }
+
+    CONFIG.logger.info(&format!(
+        "{} set the log level of '{}' to {}",
+        ctx.author().name,
+        target,
+        level.name()
+    ));
+
+    let reply = CreateReply::default()
+        .content(format!(
+            "Set the log level of `{}` to `{}`.",
+            target,
+            level.name()
+        ))
+        .ephemeral(true);
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+/// Removes the log level of a target, so it uses the level of its parent again.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn reset(
+    ctx: Context<'_>,
+    #[description = "Module path like `commands::purge` or `serenity::gateway`"]
+    #[max_length = 100]
+    target: String,
+) -> Result<(), Error> {
+    let target = target.trim();
+
+    let content = if CONFIG.logger.reset_target_level(target) {
+        CONFIG.logger.info(&format!(
+            "{} reset the log level of '{}'",
+            ctx.author().name,
+            target
+        ));
+        format!("Reset the log level of `{}`.", target)
+    } else {
+        format!(":x: `{}` has no log level of its own.", target)
+    };
+
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+pub static EXPORT: CommandsExport = &[loglevel];
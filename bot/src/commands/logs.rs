@@ -0,0 +1,242 @@
+use super::loglevel::Level;
+use crate::CONFIG;
+use poise::CreateReply;
+use poise::serenity_prelude::{
+    ComponentInteractionCollector, CreateActionRow, CreateAttachment, CreateButton, CreateEmbed,
+    CreateEmbedFooter, CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use std::io;
+use std::time::Duration;
+use utils::EMBED_DESCRIPTION_LIMIT;
+use utils::logging::{LogEntry, LogQuery, parse_time};
+use utils::shared_types::{CommandsExport, Context, Error};
+
+/// The number of entries `/logs tail` shows by default
+const DEFAULT_TAIL_ENTRIES: u32 = 20;
+
+/// The number of entries `/logs search` returns by default
+const DEFAULT_SEARCH_LIMIT: u32 = 100;
+
+/// Pages are shown in a code block in the embed description.
+const MAX_PAGE_LENGTH: usize = EMBED_DESCRIPTION_LIMIT - "```\n\n```".len();
+
+/// Results with more pages are attached as a file instead.
+const MAX_PAGES: usize = 25;
+
+/// How long the page buttons keep working after the last press
+const PAGINATION_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Shows the log files.
+#[poise::command(
+    slash_command,
+    guild_only,
+    owners_only,
+    subcommands("tail", "search"),
+    subcommand_required
+)]
+pub async fn logs(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Shows the last entries of the current log file.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn tail(
+    ctx: Context<'_>,
+    #[description = "The number of entries to show (default: 20)"]
+    #[min = 1]
+    #[max = 1000]
+    entries: Option<u32>,
+    #[description = "Attach the entries as a file instead of showing them"] file: Option<bool>,
+) -> Result<(), Error> {
+    let count = entries.unwrap_or(DEFAULT_TAIL_ENTRIES) as usize;
+    let result = tokio::task::spawn_blocking(move || CONFIG.logger.tail(count)).await?;
+
+    send_entries(ctx, "Latest Logs", result, file.unwrap_or(false)).await
+}
+
+/// Searches the current and rotated log files.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn search(
+    ctx: Context<'_>,
+    #[description = "Text the entries contain, ignoring case"]
+    #[max_length = 100]
+    text: Option<String>,
+    #[description = "The minimum level of the entries"] level: Option<Level>,
+    #[description = "Only entries after this time, e.g. `2h`, `3d`, `2024-05-01` or \
+                     `2024-05-01 13:37`"]
+    since: Option<String>,
+    #[description = "Only entries before this time, e.g. `30m` or `2024-05-01 18:00`"]
+    until: Option<String>,
+    #[description = "The maximum number of entries, the newest are shown (default: 100)"]
+    #[min = 1]
+    #[max = 1000]
+    limit: Option<u32>,
+    #[description = "Attach the entries as a file instead of showing them"] file: Option<bool>,
+) -> Result<(), Error> {
+    let timezone = CONFIG.logger.timezone();
+    let mut times = Vec::new();
+    for input in [&since, &until] {
+        let time = match input {
+            Some(input) => match parse_time(input, timezone) {
+                Some(time) => Some(time),
+                None => {
+                    let reply = CreateReply::default()
+                        .content(format!(
+                            ":x: Invalid time `{}`. Use a duration like `30m`, `2h`, `3d` or \
+                             `1w`, or a date like `2024-05-01` or `2024-05-01 13:37`.",
+                            input
+                        ))
+                        .ephemeral(true);
+                    ctx.send(reply).await?;
+                    return Ok(());
+                }
+            },
+            None => None,
+        };
+        times.push(time);
+    }
+
+    let query = LogQuery {
+        min_level: level.map(Into::into),
+        since: times[0],
+        until: times[1],
+        contains: text.filter(|text| !text.is_empty()),
+        include_rotated: true,
+    };
+    let limit = limit.unwrap_or(DEFAULT_SEARCH_LIMIT) as usize;
+    let result = tokio::task::spawn_blocking(move || CONFIG.logger.search(&query, limit)).await?;
+
+    send_entries(ctx, "Log Search", result, file.unwrap_or(false)).await
+}
+
+/// Replies with the entries as a paginated embed, or as a file if requested or if there are too
+/// many of them.
+async fn send_entries(
+    ctx: Context<'_>,
+    title: &str,
+    result: io::Result<Vec<LogEntry>>,
+    as_file: bool,
+) -> Result<(), Error> {
+    let entries = match result {
+        Ok(entries) if entries.is_empty() => {
+            let reply = CreateReply::default()
+                .content("No log entries found.")
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+        Ok(entries) => entries,
+        Err(e) => {
+            let reply = CreateReply::default()
+                .content(format!(":x: Could not read the logs: {}", e))
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    let pages = paginate(&entries);
+    if as_file || pages.len() > MAX_PAGES {
+        let text = entries
+            .iter()
+            .map(|entry| entry.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n");
+        let reply = CreateReply::default()
+            .content(format!("**{}:** {} entries", title, entries.len()))
+            .attachment(CreateAttachment::bytes(text.into_bytes(), "logs.txt"))
+            .ephemeral(true);
+        ctx.send(reply).await?;
+        return Ok(());
+    }
+
+    let embed = |page: usize| {
+        CreateEmbed::new()
+            .title(title)
+            .description(format!("```\n{}\n```", pages[page]))
+            .footer(CreateEmbedFooter::new(format!(
+                "Page {}/{}, {} entries",
+                page + 1,
+                pages.len(),
+                entries.len()
+            )))
+    };
+
+    // The last page has the newest entries
+    let mut page = pages.len() - 1;
+    let mut reply = CreateReply::default().embed(embed(page)).ephemeral(true);
+    if pages.len() == 1 {
+        ctx.send(reply).await?;
+        return Ok(());
+    }
+
+    let ctx_id = ctx.id();
+    let previous_id = format!("{}previous", ctx_id);
+    let next_id = format!("{}next", ctx_id);
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(&previous_id).emoji('◀'),
+        CreateButton::new(&next_id).emoji('▶'),
+    ]);
+    reply = reply.components(vec![buttons]);
+    let handle = ctx.send(reply).await?;
+
+    while let Some(press) = ComponentInteractionCollector::new(ctx)
+        .filter(move |press| press.data.custom_id.starts_with(&ctx_id.to_string()))
+        .timeout(PAGINATION_TIMEOUT)
+        .await
+    {
+        if press.data.custom_id == next_id {
+            page = (page + 1) % pages.len();
+        } else if press.data.custom_id == previous_id {
+            page = page.checked_sub(1).unwrap_or(pages.len() - 1);
+        } else {
+            continue;
+        }
+
+        press
+            .create_response(
+                ctx.serenity_context(),
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new().embed(embed(page)),
+                ),
+            )
+            .await?;
+    }
+
+    // Remove the buttons once they stop working
+    let reply = CreateReply::default()
+        .embed(embed(page))
+        .components(Vec::new());
+    handle.edit(ctx, reply).await?;
+
+    Ok(())
+}
+
+/// Splits the entries into pages that fit into an embed. Entries that are too long on their own
+/// are shortened.
+fn paginate(entries: &[LogEntry]) -> Vec<String> {
+    let mut pages = Vec::new();
+    let mut page = String::new();
+
+    for entry in entries {
+        // Would end the code block early
+        let mut text = entry.text.replace("```", "`\u{200b}``");
+        if text.chars().count() > MAX_PAGE_LENGTH {
+            text = text.chars().take(MAX_PAGE_LENGTH - 1).collect();
+            text.push('…');
+        }
+
+        if !page.is_empty() && page.chars().count() + text.chars().count() + 1 > MAX_PAGE_LENGTH {
+            pages.push(std::mem::take(&mut page));
+        }
+        if !page.is_empty() {
+            page.push('\n');
+        }
+        page.push_str(&text);
+    }
+    pages.push(page);
+
+    pages
+}
+
+pub static EXPORT: CommandsExport = &[logs];
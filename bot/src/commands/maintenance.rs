@@ -0,0 +1,56 @@
+use crate::CONFIG;
+use poise::CreateReply;
+use utils::shared_types::{CommandsExport, Context, Error};
+
+/// Turns maintenance mode on or off.
+///
+/// While it is on, the bot stays online but only owners can use commands. Everyone else gets the
+/// `maintenance_message` from the config file. The mode is kept across restarts.
+#[poise::command(
+    slash_command,
+    guild_only,
+    owners_only,
+    subcommands("on", "off"),
+    subcommand_required
+)]
+pub async fn maintenance(_ctx: Context<'_>) -> Result<(), Error> {
+    Ok(())
+}
+
+/// Turns maintenance mode on, only owners can use commands.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn on(ctx: Context<'_>) -> Result<(), Error> {
+    set(ctx, true).await
+}
+
+/// Turns maintenance mode off, everyone can use commands again.
+#[poise::command(slash_command, guild_only, owners_only)]
+async fn off(ctx: Context<'_>) -> Result<(), Error> {
+    set(ctx, false).await
+}
+
+async fn set(ctx: Context<'_>, enabled: bool) -> Result<(), Error> {
+    let maintenance = &ctx.data().maintenance;
+    let state = if enabled { "on" } else { "off" };
+    let content = if maintenance.is_enabled() == enabled {
+        format!("Maintenance mode is already {}.", state)
+    } else {
+        maintenance.set(&ctx.data().storage, enabled)?;
+        CONFIG.logger.info(&format!(
+            "`{}` turned maintenance mode {}",
+            ctx.author().name,
+            state
+        ));
+        if enabled {
+            ":construction: Maintenance mode is on, only owners can use commands.".to_string()
+        } else {
+            ":white_check_mark: Maintenance mode is off.".to_string()
+        }
+    };
+
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    ctx.send(reply).await?;
+    Ok(())
+}
+
+pub static EXPORT: CommandsExport = &[maintenance];
@@ -19,4 +19,17 @@ macro_rules! all_commands {
     };
 }
 
-all_commands![avatar, embed, help, ping, purge, say, shutdown];
+all_commands![
+    avatar,
+    embed,
+    help,
+    loglevel,
+    logs,
+    maintenance,
+    ping,
+    purge,
+    reload_config,
+    restart,
+    say,
+    shutdown
+];
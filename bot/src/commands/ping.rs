@@ -1,7 +1,8 @@
+use crate::CONFIG;
 use utils::shared_types::{CommandsExport, Context, Error};
 
 async fn check(ctx: Context<'_>) -> Result<bool, Error> {
-    Ok(utils::check(CONFIG.commands.avatar, &ctx, &CONFIG.logger).await)
+    Ok(utils::check(|commands| &commands.ping, &ctx, &CONFIG).await)
 }
 
 /// Sends back "Pong! 🏓"
@@ -1,130 +1,563 @@
-use chrono_tz::Europe::Berlin;
 use poise::serenity_prelude::{
-    CreateAttachment, CreateEmbedAuthor, GetMessages, Timestamp,
+    ChannelId, CreateEmbedAuthor, Error as SerenityError, GetMessages, GuildId, HttpError, Message,
+    MessageId, Timestamp, User, UserId,
     builder::{CreateEmbed, CreateMessage},
 };
+use regex::Regex;
+use std::time::Duration;
 
 use crate::CONFIG;
+use archive::{ArchiveFormat, ArchiveInfo};
+use utils::confirm::{Confirmation, confirm};
 use utils::shared_types::{CommandsExport, Context, Error};
 
+mod archive;
+
+/// Messages older than this cannot be bulk deleted. Slightly less than the 14 days Discord allows,
+/// so messages do not age past the limit while the purge is running.
+const BULK_DELETE_MAX_AGE: chrono::TimeDelta = chrono::TimeDelta::hours(14 * 24 - 1);
+
+/// Delay between deleting messages that are too old to be bulk deleted
+const SINGLE_DELETE_INTERVAL: Duration = Duration::from_millis(1100);
+
+/// How many single deletes happen between updates of the progress reply
+const PROGRESS_UPDATE_INTERVAL: usize = 10;
+
+/// The JSON error code Discord returns for messages that do not exist
+const UNKNOWN_MESSAGE_CODE: isize = 10008;
+
 async fn check(ctx: Context<'_>) -> Result<bool, Error> {
-    Ok(utils::check(CONFIG.commands.purge, &ctx, &CONFIG.logger).await)
+    Ok(utils::check(|commands| &commands.purge, &ctx, &CONFIG).await)
+}
+
+/// Filters for the messages deleted by [`purge`]. A message has to match every set filter.
+#[derive(Debug)]
+struct PurgeFilter {
+    user: Option<UserId>,
+    bots: bool,
+    regex: Option<Regex>,
+    attachments: bool,
+    embeds: bool,
+    links: bool,
+    exclude_pinned: bool,
+}
+
+impl PurgeFilter {
+    fn matches(&self, message: &Message, link_regex: &Regex) -> bool {
+        if self.user.is_some_and(|user| message.author.id != user) {
+            return false;
+        }
+        if self.bots && !message.author.bot {
+            return false;
+        }
+        if let Some(regex) = &self.regex
+            && !regex.is_match(&message.content)
+        {
+            return false;
+        }
+        if self.attachments && message.attachments.is_empty() {
+            return false;
+        }
+        if self.embeds && message.embeds.is_empty() {
+            return false;
+        }
+        if self.links && !link_regex.is_match(&message.content) {
+            return false;
+        }
+        if self.exclude_pinned && message.pinned {
+            return false;
+        }
+        true
+    }
+
+    /// Describes the set filters for the purge log, one per line.
+    fn describe(&self) -> String {
+        let mut filters = Vec::new();
+        if let Some(user) = self.user {
+            filters.push(format!("From <@{}>", user));
+        }
+        if self.bots {
+            filters.push(String::from("From bots"));
+        }
+        if let Some(regex) = &self.regex {
+            filters.push(format!("Matching `{}`", regex));
+        }
+        if self.attachments {
+            filters.push(String::from("With attachments"));
+        }
+        if self.embeds {
+            filters.push(String::from("With embeds"));
+        }
+        if self.links {
+            filters.push(String::from("Containing links"));
+        }
+        if self.exclude_pinned {
+            filters.push(String::from("Not pinned"));
+        }
+        filters.join("\n")
+    }
+}
+
+/// The part of the channel history scanned by [`purge`].
+#[derive(Debug, Default)]
+struct PurgeRange {
+    /// Only scan messages before this message
+    before: Option<MessageId>,
+    /// Only scan messages after this message
+    after: Option<MessageId>,
+    /// Only scan the messages around this message. Cannot be combined with the others.
+    around: Option<MessageId>,
+}
+
+impl PurgeRange {
+    fn is_set(&self) -> bool {
+        self.before.is_some() || self.after.is_some() || self.around.is_some()
+    }
+
+    /// Describes the range for the purge log, one bound per line.
+    fn describe(&self, channel_id: ChannelId, guild_id: GuildId) -> String {
+        let link = |message_id: MessageId| message_id.link(channel_id, Some(guild_id));
+        let mut bounds = Vec::new();
+        if let Some(before) = self.before {
+            bounds.push(format!("Before {}", link(before)));
+        }
+        if let Some(after) = self.after {
+            bounds.push(format!("After {}", link(after)));
+        }
+        if let Some(around) = self.around {
+            bounds.push(format!("Around {}", link(around)));
+        }
+        bounds.join("\n")
+    }
+}
+
+/// Parses a message ID or a link to a message in the given channel.
+fn parse_message(input: &str, channel_id: ChannelId) -> Result<MessageId, String> {
+    let input = input.trim();
+    let id = match input.rsplit_once('/') {
+        Some((rest, id)) => {
+            let link_channel = rest.rsplit('/').next().unwrap_or_default();
+            if link_channel != channel_id.to_string() {
+                return Err(format!("The message '{}' is not in this channel.", input));
+            }
+            id
+        }
+        None => input,
+    };
+
+    id.parse::<u64>()
+        .ok()
+        .filter(|id| *id != 0)
+        .map(MessageId::new)
+        .ok_or_else(|| format!("'{}' is not a valid message ID or link.", input))
 }
 
 /// Deletes the specified amount of messages in the current channel.
 ///
-/// Bulk Deletes up to 100 messages in a channel.
+/// Deletes messages in a channel, optionally only those matching filters.
 ///
-/// To delete more than 100 messages, run the command multiple times. If the specified amount of \
-/// messages is more than the amount of messages in the current channel, it will delete all \
-/// messages in the channel and use that amount for the reply and log instead of the specified \
-/// amount.
-/// Note: Messages older than 14 days cannot be bulk deleted due to Discord limitations.
+/// The channel history is scanned page by page until the specified amount of messages was \
+/// found. If the channel has fewer messages, all of them are deleted and that amount is used for \
+/// the reply and log instead of the specified amount. The maximum amount and the maximum number \
+/// of scanned messages are configured per server.
+/// When filters are set, only the matching messages are deleted. The reply and log report how \
+/// many messages were scanned and how many were deleted.
+/// Messages younger than 14 days are bulk deleted. Older messages cannot be bulk deleted due to \
+/// Discord limitations, so they are deleted one by one, which is much slower. The reply shows \
+/// the progress while deleting.
+/// Use `before`, `after` or `around` with a message ID or link to only delete messages in that \
+/// range. `before` and `after` can be combined, `around` scans at most 100 messages. If a range \
+/// is given, the amount defaults to the maximum amount.
+/// Purges of more than the configured confirmation threshold have to be confirmed first.
+/// With `dry_run`, nothing is deleted and the messages that would be deleted are listed instead.
+/// The deleted messages are archived in the purge log as plain text, JSON or an HTML transcript \
+/// including authors, avatars, embeds, attachment links and replies. Attachments themselves are \
+/// not archived, their links stop working once the messages are deleted.
 #[poise::command(slash_command, guild_only, check = check)]
+#[allow(clippy::too_many_arguments)]
 pub async fn purge(
     ctx: Context<'_>,
-    #[description = "The amount of messages to delete"]
+
+    #[description = "The amount of messages to delete. Defaults to the maximum if a range is set."]
     #[min = 1]
-    #[max = 100]
-    amount: u8,
+    amount: Option<u32>,
+
+    #[description = "Only delete messages from this user."] user: Option<User>,
+
+    #[description = "Only delete messages from bots."] bots: Option<bool>,
+
+    #[description = "Only delete messages whose content matches this regex."]
+    #[min_length = 1]
+    regex: Option<String>,
+
+    #[description = "Only delete messages with attachments."] attachments: Option<bool>,
+
+    #[description = "Only delete messages with embeds."] embeds: Option<bool>,
+
+    #[description = "Only delete messages containing links."] links: Option<bool>,
+
+    #[description = "Do not delete pinned messages."] exclude_pinned: Option<bool>,
+
+    #[description = "Only delete messages before this message (ID or link)."]
+    #[min_length = 1]
+    before: Option<String>,
+
+    #[description = "Only delete messages after this message (ID or link)."]
+    #[min_length = 1]
+    after: Option<String>,
+
+    #[description = "Only delete messages around this message (ID or link)."]
+    #[min_length = 1]
+    around: Option<String>,
+
+    #[description = "Only list the messages that would be deleted."] dry_run: Option<bool>,
+
+    #[description = "The file format of the archive of deleted messages. Defaults to text."]
+    archive_format: Option<ArchiveFormat>,
 ) -> Result<(), Error> {
     let channel_id = ctx.channel_id();
-    let get_messages = GetMessages::new().limit(amount);
-    let messages = channel_id
-        .messages(&ctx.serenity_context().http, get_messages)
-        .await?;
+    let parse = |input: Option<String>| {
+        input
+            .map(|input| parse_message(&input, channel_id))
+            .transpose()
+    };
+    let range = match (parse(before), parse(after), parse(around)) {
+        (Ok(before), Ok(after), Ok(around)) => PurgeRange {
+            before,
+            after,
+            around,
+        },
+        (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+            let reply = poise::CreateReply::default()
+                .content(format!(":x: {}", e))
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    if range.around.is_some() && (range.before.is_some() || range.after.is_some()) {
+        let reply = poise::CreateReply::default()
+            .content(":x: `around` cannot be combined with `before` or `after`.")
+            .ephemeral(true);
+        ctx.send(reply).await?;
+        return Ok(());
+    }
 
-    channel_id
-        .delete_messages(&ctx.serenity_context().http, &messages)
-        .await?;
+    let regex = match regex.map(|regex| Regex::new(&regex)).transpose() {
+        Ok(regex) => regex,
+        Err(e) => {
+            let reply = poise::CreateReply::default()
+                .content(format!(":x: Invalid regex: {}", e))
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+    };
 
-    let message_count = messages.len();
+    let filter = PurgeFilter {
+        user: user.map(|user| user.id),
+        bots: bots.unwrap_or(false),
+        regex,
+        attachments: attachments.unwrap_or(false),
+        embeds: embeds.unwrap_or(false),
+        links: links.unwrap_or(false),
+        exclude_pinned: exclude_pinned.unwrap_or(false),
+    };
+    let filter_description = filter.describe();
 
-    let reply = poise::CreateReply::default()
-        .content(format!("Deleted {} messages.", message_count))
+    // Safe to unwrap since the command is guild only
+    let guild_id = ctx.guild_id().unwrap();
+    let (max_amount, max_scan, confirm_threshold) = match CONFIG.settings().guild(guild_id) {
+        Some(guild) => (
+            guild.purge_max_amount,
+            guild.purge_max_scan,
+            guild.purge_confirm_threshold,
+        ),
+        // The guild was removed by a reload since the check
+        None => {
+            let reply = poise::CreateReply::default()
+                .content(":x: This server is not configured.")
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+    };
+
+    let amount = match amount {
+        Some(amount) => amount,
+        None if range.is_set() => max_amount,
+        None => {
+            let reply = poise::CreateReply::default()
+                .content(":x: Specify an amount or a range of messages to delete.")
+                .ephemeral(true);
+            ctx.send(reply).await?;
+            return Ok(());
+        }
+    };
+    let dry_run = dry_run.unwrap_or(false);
+
+    if amount > max_amount {
+        let reply = poise::CreateReply::default()
+            .content(format!(
+                ":x: You can delete at most {} messages at once in this server.",
+                max_amount
+            ))
+            .ephemeral(true);
+        ctx.send(reply).await?;
+        return Ok(());
+    }
+
+    let scanning = poise::CreateReply::default()
+        .content("Scanning messages...")
         .ephemeral(true);
-    ctx.send(reply).await?;
-
-    let mut log_content = String::new();
-
-    for message in messages.iter().rev() {
-        let timestamp = message.timestamp.to_utc().with_timezone(&Berlin);
-        let timestamp: String = timestamp.format("%d/%m/%Y %I:%M:%S %p %Z").to_string();
-        let author = &message.author.name;
-        let content = &message.content;
-        let attachment_count = message.attachments.len();
-        let embed_count = message.embeds.len();
-
-        log_content.push_str(
-            format!(
-                "{}\n@{}{}{}{}\n\n\n",
-                timestamp,
-                author,
-                if content.is_empty() {
-                    String::new()
-                } else {
-                    format!("\n{}", content)
-                },
-                if attachment_count > 0 {
-                    format!("\n-[{} attachment(s)]", attachment_count)
-                } else {
-                    String::new()
-                },
-                if embed_count > 0 {
-                    format!("\n-[{} embed(s)]", embed_count)
-                } else {
-                    String::new()
-                }
-            )
-            .as_str(),
+    let progress = if !dry_run && amount > confirm_threshold {
+        let prompt = format!(
+            "Delete up to {} messages in <#{}>? This cannot be undone.",
+            amount, channel_id
         );
+        let Confirmation::Confirmed(progress) = confirm(ctx, prompt).await? else {
+            return Ok(());
+        };
+        progress.edit(ctx, scanning).await?;
+        progress
+    } else {
+        ctx.send(scanning).await?
+    };
+
+    let http = &ctx.serenity_context().http;
+
+    // Safe to unwrap since the regex is valid
+    let link_regex = Regex::new(r"https?://\S+").unwrap();
+    let mut scanned_count = 0;
+    let mut messages: Vec<Message> = Vec::new();
+    let mut before = range.before;
+    // Without filters every scanned message is deleted, so only the amount limits the scan
+    let scan_limit = if filter_description.is_empty() {
+        u32::MAX
+    } else {
+        max_scan
+    };
+
+    'scan: while messages.len() < amount as usize && scanned_count < scan_limit {
+        // Without filters every message matches, so there is no need to fetch more than needed
+        let remaining = if filter_description.is_empty() {
+            amount as usize - messages.len()
+        } else {
+            (scan_limit - scanned_count) as usize
+        };
+        let mut get_messages = GetMessages::new().limit(remaining.min(100) as u8);
+        if let Some(around) = range.around {
+            get_messages = get_messages.around(around);
+        } else if let Some(before) = before {
+            get_messages = get_messages.before(before);
+        }
+
+        let page = channel_id.messages(http, get_messages).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        before = Some(last.id);
+
+        for message in page {
+            if messages.len() >= amount as usize || scanned_count >= scan_limit {
+                break 'scan;
+            }
+            // Pages are ordered from newest to oldest, so everything after this is out of range
+            if range.after.is_some_and(|after| message.id <= after) {
+                break 'scan;
+            }
+            scanned_count += 1;
+            if filter.matches(&message, &link_regex) {
+                messages.push(message);
+            }
+        }
+
+        // Around only returns a single page
+        if range.around.is_some() {
+            break;
+        }
+    }
+
+    let message_count = messages.len();
+
+    let channel_name = channel_id.name(&ctx.http()).await.unwrap_or_default();
+    let archive_info = ArchiveInfo {
+        guild_id,
+        channel_id,
+        channel_name: &channel_name,
+        purged_by: ctx.author(),
+    };
+    let archive_format = archive_format.unwrap_or_default();
+
+    if dry_run {
+        let reply = poise::CreateReply::default()
+            .content(format!(
+                "Dry run: would delete {} messages (scanned {}).",
+                message_count, scanned_count
+            ))
+            .ephemeral(true);
+        progress.edit(ctx, reply).await?;
+        if !messages.is_empty() {
+            // Edits of the initial response cannot add attachments, so the list is a followup
+            let list = poise::CreateReply::default()
+                .attachment(archive::create(archive_format, &messages, &archive_info))
+                .ephemeral(true);
+            ctx.send(list).await?;
+        }
+        return Ok(());
+    }
+
+    let bulk_deletable_after = Timestamp::from(chrono::Utc::now() - BULK_DELETE_MAX_AGE);
+    let (recent, old): (Vec<&Message>, Vec<&Message>) = messages
+        .iter()
+        .partition(|message| message.timestamp > bulk_deletable_after);
+
+    let progress_reply = |deleted_count: usize| {
+        poise::CreateReply::default()
+            .content(format!(
+                "Deleting messages... {}/{} (scanned {}).",
+                deleted_count, message_count, scanned_count
+            ))
+            .ephemeral(true)
+    };
+
+    // Messages that were deleted, by the purge or by someone else in the meantime
+    let mut deleted_ids: Vec<MessageId> = Vec::new();
+    let mut failure: Option<SerenityError> = None;
+
+    for chunk in recent.chunks(100) {
+        let ids: Vec<MessageId> = chunk.iter().map(|message| message.id).collect();
+        match channel_id.delete_messages(http, &ids).await {
+            Ok(()) => {}
+            Err(e) if is_unknown_message(&e) => {}
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+        deleted_ids.extend(ids);
+        // Progress updates are best effort, the interaction token might have expired
+        progress
+            .edit(ctx, progress_reply(deleted_ids.len()))
+            .await
+            .ok();
     }
 
-    let attachment = CreateAttachment::bytes(
-        std::borrow::Cow::Owned(log_content.as_bytes().to_vec()),
-        format!(
-            "purge_log_{}.txt",
-            chrono::Utc::now().format("%Y%m%d%H%M%S")
+    if failure.is_none() {
+        for (i, message) in old.iter().enumerate() {
+            if i > 0 {
+                tokio::time::sleep(SINGLE_DELETE_INTERVAL).await;
+            }
+            match channel_id.delete_message(http, message.id).await {
+                Ok(()) => {}
+                Err(e) if is_unknown_message(&e) => {}
+                Err(e) => {
+                    failure = Some(e);
+                    break;
+                }
+            }
+            deleted_ids.push(message.id);
+            if deleted_ids.len().is_multiple_of(PROGRESS_UPDATE_INTERVAL) {
+                progress
+                    .edit(ctx, progress_reply(deleted_ids.len()))
+                    .await
+                    .ok();
+            }
+        }
+    }
+
+    // Only the deleted messages are archived and logged
+    messages.retain(|message| deleted_ids.contains(&message.id));
+    let deleted_count = messages.len();
+
+    let content = match &failure {
+        None => format!(
+            "Deleted {} messages (scanned {}).",
+            deleted_count, scanned_count
         ),
-    );
+        Some(_) => format!(
+            ":x: Deleted {} of {} messages (scanned {}) before an error occurred.",
+            deleted_count, message_count, scanned_count
+        ),
+    };
+    let reply = poise::CreateReply::default()
+        .content(content)
+        .ephemeral(true);
+    progress.edit(ctx, reply).await.ok();
+
+    if messages.is_empty() {
+        return failure.map_or(Ok(()), |e| Err(e.into()));
+    }
+
+    let attachment = archive::create(archive_format, &messages, &archive_info);
 
     let channel_mention = format!("<#{}>", channel_id);
     let log_description = format!(
-        "Purged {} messages in channel {}",
-        message_count, channel_mention
+        "Purged {} of {} scanned messages in channel {}",
+        deleted_count, scanned_count, channel_mention
     );
     let log_author_icon = ctx
         .author()
         .avatar_url()
         .unwrap_or(ctx.author().default_avatar_url());
 
-    let log_embed = CreateEmbed::default()
+    let mut log_embed = CreateEmbed::default()
         .author(CreateEmbedAuthor::new(&ctx.author().name).icon_url(log_author_icon))
         .title("Purge Command Executed")
         .description(log_description)
         .color(0xFF0000)
         .timestamp(Timestamp::now());
 
+    if !filter_description.is_empty() {
+        log_embed = log_embed.field("Filters", filter_description, false);
+    }
+
+    if range.is_set() {
+        log_embed = log_embed.field("Range", range.describe(channel_id, guild_id), false);
+    }
+
     let log = CreateMessage::default()
         .embed(log_embed)
         .add_file(attachment);
 
-    CONFIG
-        .purge_command_channel
-        .send_message(&ctx.serenity_context().http, log)
-        .await?;
+    if let Some(guild) = CONFIG.settings().guild(guild_id) {
+        guild
+            .purge_command_channel
+            .send_message(&ctx.serenity_context().http, log)
+            .await?;
+    }
 
-    let channel_name = channel_id.name(&ctx.http()).await.unwrap_or_default();
+    CONFIG.logger.info_with(
+        &format!(
+            "{} purged {} of {} scanned messages in channel '{}' ({})",
+            ctx.author().name,
+            deleted_count,
+            scanned_count,
+            channel_name,
+            channel_id
+        ),
+        &[
+            ("command", &"purge"),
+            ("user_id", &ctx.author().id),
+            ("guild_id", &guild_id),
+            ("channel_id", &channel_id),
+            ("deleted", &deleted_count),
+            ("scanned", &scanned_count),
+        ],
+    );
 
-    CONFIG.logger.info(&format!(
-        "{} purged {} messages in channel '{}' ({})",
-        ctx.author().name,
-        message_count,
-        channel_name,
-        channel_id
-    ));
+    failure.map_or(Ok(()), |e| Err(e.into()))
+}
 
-    Ok(())
+/// Whether the error means the message does not exist, e.g. because someone else deleted it
+/// after the scan.
+fn is_unknown_message(error: &SerenityError) -> bool {
+    matches!(
+        error,
+        SerenityError::Http(HttpError::UnsuccessfulRequest(response))
+            if response.error.code == UNKNOWN_MESSAGE_CODE
+    )
 }
 
 pub static EXPORT: CommandsExport = &[purge];
@@ -0,0 +1,418 @@
+//! Creates the archive of the messages deleted by a purge.
+
+use chrono_tz::Europe::Berlin;
+use poise::serenity_prelude::{
+    ChannelId, CreateAttachment, Embed, GuildId, Message, MessageId, Timestamp, User, UserId,
+};
+use serde::Serialize;
+
+/// The file format of a purge archive.
+#[derive(Debug, Clone, Copy, Default, poise::ChoiceParameter)]
+pub enum ArchiveFormat {
+    /// Human readable plain text
+    #[default]
+    #[name = "Text"]
+    Text,
+    /// Structured JSON, including the raw embeds
+    #[name = "JSON"]
+    Json,
+    /// HTML transcript that renders like the channel. Avatars, attachments and embed images are
+    /// only linked, attachments stop loading once their messages are deleted.
+    #[name = "HTML"]
+    Html,
+}
+
+impl ArchiveFormat {
+    fn extension(self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "json",
+            Self::Html => "html",
+        }
+    }
+}
+
+/// Where the archived messages were purged and by whom.
+pub struct ArchiveInfo<'a> {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub channel_name: &'a str,
+    pub purged_by: &'a User,
+}
+
+/// Creates the archive file of the given messages, which are ordered from newest to oldest. The
+/// archive lists them from oldest to newest.
+pub fn create(format: ArchiveFormat, messages: &[Message], info: &ArchiveInfo) -> CreateAttachment {
+    let content = match format {
+        ArchiveFormat::Text => text(messages),
+        ArchiveFormat::Json => json(messages, info),
+        ArchiveFormat::Html => html(messages, info),
+    };
+
+    CreateAttachment::bytes(
+        std::borrow::Cow::Owned(content.into_bytes()),
+        format!(
+            "purge_log_{}.{}",
+            chrono::Utc::now().format("%Y%m%d%H%M%S"),
+            format.extension()
+        ),
+    )
+}
+
+fn format_timestamp(timestamp: &Timestamp) -> String {
+    let timestamp = timestamp.to_utc().with_timezone(&Berlin);
+    timestamp.format("%d/%m/%Y %I:%M:%S %p %Z").to_string()
+}
+
+fn avatar_url(user: &User) -> String {
+    user.avatar_url().unwrap_or(user.default_avatar_url())
+}
+
+// ┌──────┐
+// │ Text │
+// └──────┘
+
+fn text(messages: &[Message]) -> String {
+    let mut log_content = String::new();
+
+    for message in messages.iter().rev() {
+        let timestamp = format_timestamp(&message.timestamp);
+        let author = &message.author;
+
+        log_content.push_str(&format!("{}\n@{} ({})", timestamp, author.name, author.id));
+
+        if let Some(reference) = &message.message_reference
+            && let Some(message_id) = reference.message_id
+        {
+            match &message.referenced_message {
+                Some(referenced) => log_content.push_str(&format!(
+                    "\n-> Reply to @{} ({})",
+                    referenced.author.name, message_id
+                )),
+                None => log_content.push_str(&format!("\n-> Reply to {}", message_id)),
+            }
+        }
+
+        if !message.content.is_empty() {
+            log_content.push_str(&format!("\n{}", message.content));
+        }
+
+        for attachment in &message.attachments {
+            log_content.push_str(&format!(
+                "\n-[attachment] {}: {}",
+                attachment.filename, attachment.url
+            ));
+        }
+
+        for embed in &message.embeds {
+            let mut parts = Vec::new();
+            parts.extend(embed.title.as_deref());
+            parts.extend(embed.description.as_deref());
+            for field in &embed.fields {
+                parts.push(&field.name);
+                parts.push(&field.value);
+            }
+            log_content.push_str(&format!("\n-[embed] {}", parts.join(" | ")));
+        }
+
+        log_content.push_str("\n\n\n");
+    }
+
+    log_content
+}
+
+// ┌──────┐
+// │ JSON │
+// └──────┘
+
+#[derive(Serialize)]
+struct JsonArchive<'a> {
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    channel_name: &'a str,
+    purged_by: JsonUser<'a>,
+    purged_at: Timestamp,
+    messages: Vec<JsonMessage<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonUser<'a> {
+    id: UserId,
+    name: &'a str,
+    bot: bool,
+    avatar_url: String,
+}
+
+#[derive(Serialize)]
+struct JsonMessage<'a> {
+    id: MessageId,
+    timestamp: Timestamp,
+    edited_timestamp: Option<Timestamp>,
+    author: JsonUser<'a>,
+    content: &'a str,
+    pinned: bool,
+    reply_to: Option<MessageId>,
+    attachments: Vec<JsonAttachment<'a>>,
+    embeds: &'a [Embed],
+}
+
+#[derive(Serialize)]
+struct JsonAttachment<'a> {
+    filename: &'a str,
+    url: &'a str,
+    size: u32,
+    content_type: Option<&'a str>,
+}
+
+impl<'a> From<&'a User> for JsonUser<'a> {
+    fn from(user: &'a User) -> Self {
+        Self {
+            id: user.id,
+            name: &user.name,
+            bot: user.bot,
+            avatar_url: avatar_url(user),
+        }
+    }
+}
+
+fn json(messages: &[Message], info: &ArchiveInfo) -> String {
+    let archive = JsonArchive {
+        guild_id: info.guild_id,
+        channel_id: info.channel_id,
+        channel_name: info.channel_name,
+        purged_by: info.purged_by.into(),
+        purged_at: Timestamp::now(),
+        messages: messages
+            .iter()
+            .rev()
+            .map(|message| JsonMessage {
+                id: message.id,
+                timestamp: message.timestamp,
+                edited_timestamp: message.edited_timestamp,
+                author: (&message.author).into(),
+                content: &message.content,
+                pinned: message.pinned,
+                reply_to: message
+                    .message_reference
+                    .as_ref()
+                    .and_then(|reference| reference.message_id),
+                attachments: message
+                    .attachments
+                    .iter()
+                    .map(|attachment| JsonAttachment {
+                        filename: &attachment.filename,
+                        url: &attachment.url,
+                        size: attachment.size,
+                        content_type: attachment.content_type.as_deref(),
+                    })
+                    .collect(),
+                embeds: &message.embeds,
+            })
+            .collect(),
+    };
+
+    // Serializing plain structs and serenity models cannot fail
+    serde_json::to_string_pretty(&archive).unwrap_or_default()
+}
+
+// ┌──────┐
+// │ HTML │
+// └──────┘
+
+const HTML_STYLE: &str = "
+body { background: #313338; color: #dbdee1; font-family: sans-serif; margin: 0; padding: 16px; }
+h1 { font-size: 18px; margin: 0 0 4px; }
+.info { color: #949ba4; font-size: 13px; margin-bottom: 16px; }
+.message { display: flex; gap: 12px; padding: 6px 0; }
+.avatar { width: 40px; height: 40px; border-radius: 50%; }
+.author { font-weight: bold; color: #f2f3f5; }
+.bot { background: #5865f2; color: #fff; border-radius: 3px; font-size: 10px; padding: 1px 4px; }
+.time, .id { color: #949ba4; font-size: 12px; margin-left: 6px; }
+.reply { color: #949ba4; font-size: 13px; margin-bottom: 2px; }
+.content { white-space: pre-wrap; word-wrap: break-word; }
+.attachment img { max-width: 400px; max-height: 300px; border-radius: 4px; margin-top: 4px; }
+.embed { background: #2b2d31; border-left: 4px solid #1e1f22; border-radius: 4px;
+    padding: 8px 12px; margin-top: 4px; max-width: 520px; }
+.embed-title { font-weight: bold; color: #f2f3f5; }
+.embed-field { margin-top: 6px; }
+.embed-field-name { font-weight: bold; font-size: 13px; }
+.embed-footer { color: #949ba4; font-size: 12px; margin-top: 6px; }
+.embed img { max-width: 100%; border-radius: 4px; margin-top: 6px; }
+.embed .thumbnail { float: right; max-width: 80px; margin-left: 8px; }
+a { color: #00a8fc; }
+";
+
+fn escape(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            c => output.push(c),
+        }
+    }
+    output
+}
+
+fn html(messages: &[Message], info: &ArchiveInfo) -> String {
+    let mut output = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Purge of #{}</title>\n\
+        <style>{}</style>\n</head>\n<body>\n<h1>Purged messages from #{}</h1>\n\
+        <div class=\"info\">{} messages, purged by {} ({}) on {}</div>\n",
+        escape(info.channel_name),
+        HTML_STYLE,
+        escape(info.channel_name),
+        messages.len(),
+        escape(&info.purged_by.name),
+        info.purged_by.id,
+        format_timestamp(&Timestamp::now()),
+    );
+
+    if messages
+        .iter()
+        .any(|message| !message.attachments.is_empty())
+    {
+        output.push_str(
+            "<div class=\"info\">Attachments are only linked, not stored in this file. Discord \
+            stops serving them once their messages are deleted.</div>\n",
+        );
+    }
+
+    for message in messages.iter().rev() {
+        html_message(&mut output, message);
+    }
+
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+fn html_message(output: &mut String, message: &Message) {
+    let author = &message.author;
+
+    output.push_str(&format!(
+        "<div class=\"message\" id=\"{}\">\n<img class=\"avatar\" src=\"{}\" alt=\"\">\n<div>\n",
+        message.id,
+        escape(&avatar_url(author))
+    ));
+
+    if let Some(reference) = &message.message_reference
+        && let Some(message_id) = reference.message_id
+    {
+        let target = match &message.referenced_message {
+            Some(referenced) => format!(
+                "@{}: {}",
+                escape(&referenced.author.name),
+                escape(&referenced.content.chars().take(100).collect::<String>())
+            ),
+            None => format!("message {}", message_id),
+        };
+        output.push_str(&format!(
+            "<div class=\"reply\">&#8618; <a href=\"#{}\">{}</a></div>\n",
+            message_id, target
+        ));
+    }
+
+    output.push_str(&format!(
+        "<span class=\"author\">{}</span>{}<span class=\"time\">{}</span>\
+        <span class=\"id\">{}</span>\n",
+        escape(&author.name),
+        if author.bot {
+            " <span class=\"bot\">BOT</span>"
+        } else {
+            ""
+        },
+        format_timestamp(&message.timestamp),
+        author.id
+    ));
+
+    if !message.content.is_empty() {
+        output.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape(&message.content)
+        ));
+    }
+
+    for attachment in &message.attachments {
+        let url = escape(&attachment.url);
+        let is_image = attachment
+            .content_type
+            .as_deref()
+            .is_some_and(|content_type| content_type.starts_with("image/"));
+        output.push_str(&format!(
+            "<div class=\"attachment\"><a href=\"{}\">{}</a> ({} bytes, link only){}</div>\n",
+            url,
+            escape(&attachment.filename),
+            attachment.size,
+            if is_image {
+                format!("<br><img src=\"{}\" alt=\"\">", url)
+            } else {
+                String::new()
+            }
+        ));
+    }
+
+    for embed in &message.embeds {
+        html_embed(output, embed);
+    }
+
+    output.push_str("</div>\n</div>\n");
+}
+
+fn html_embed(output: &mut String, embed: &Embed) {
+    let color = embed
+        .colour
+        .map(|colour| format!(" style=\"border-left-color: #{}\"", colour.hex()))
+        .unwrap_or_default();
+    output.push_str(&format!("<div class=\"embed\"{}>\n", color));
+
+    if let Some(thumbnail) = &embed.thumbnail {
+        output.push_str(&format!(
+            "<img class=\"thumbnail\" src=\"{}\" alt=\"\">\n",
+            escape(&thumbnail.url)
+        ));
+    }
+
+    if let Some(author) = &embed.author {
+        output.push_str(&format!("<div>{}</div>\n", escape(&author.name)));
+    }
+
+    if let Some(title) = &embed.title {
+        let title = match &embed.url {
+            Some(url) => format!("<a href=\"{}\">{}</a>", escape(url), escape(title)),
+            None => escape(title),
+        };
+        output.push_str(&format!("<div class=\"embed-title\">{}</div>\n", title));
+    }
+
+    if let Some(description) = &embed.description {
+        output.push_str(&format!(
+            "<div class=\"content\">{}</div>\n",
+            escape(description)
+        ));
+    }
+
+    for field in &embed.fields {
+        output.push_str(&format!(
+            "<div class=\"embed-field\"><div class=\"embed-field-name\">{}</div>\
+            <div class=\"content\">{}</div></div>\n",
+            escape(&field.name),
+            escape(&field.value)
+        ));
+    }
+
+    if let Some(image) = &embed.image {
+        output.push_str(&format!("<img src=\"{}\" alt=\"\">\n", escape(&image.url)));
+    }
+
+    if let Some(footer) = &embed.footer {
+        output.push_str(&format!(
+            "<div class=\"embed-footer\">{}</div>\n",
+            escape(&footer.text)
+        ));
+    }
+
+    output.push_str("</div>\n");
+}
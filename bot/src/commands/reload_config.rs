@@ -0,0 +1,30 @@
+use crate::CONFIG;
+use poise::CreateReply;
+use utils::shared_types::{CommandsExport, Context, Error};
+
+/// Reloads the config file.
+///
+/// Re-reads the config file and applies the new command rules and log channels.
+///
+/// The result is also posted to the config reload channel. Changes to the log directory or the \
+/// configured guilds require a restart. If the new config is invalid, the current one is kept.
+#[poise::command(slash_command, guild_only, owners_only, rename = "reload-config")]
+pub async fn reload_config(ctx: Context<'_>) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let reason = format!("Triggered by `{}`", ctx.author().name);
+    let content = match utils::reload_config(ctx.http(), &CONFIG, &reason).await {
+        Ok(()) => String::from("Config reloaded."),
+        Err(e) => format!(
+            ":x: Failed to reload config, keeping the current one.\n```\n{}\n```",
+            e
+        ),
+    };
+
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    ctx.send(reply).await?;
+
+    Ok(())
+}
+
+pub static EXPORT: CommandsExport = &[reload_config];
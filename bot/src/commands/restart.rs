@@ -0,0 +1,38 @@
+use crate::CONFIG;
+use poise::CreateReply;
+use utils::confirm::{Confirmation, confirm};
+use utils::shared_types::{CommandsExport, Context, Error};
+use utils::shutdown::ShutdownKind;
+
+/// Restarts the bot.
+///
+/// The bot exits with a distinct status code, a supervisor like systemd or docker has to start it
+/// again.
+#[poise::command(slash_command, guild_only, owners_only)]
+pub async fn restart(ctx: Context<'_>) -> Result<(), Error> {
+    let Confirmation::Confirmed(handle) = confirm(ctx, "Restart the bot?").await? else {
+        return Ok(());
+    };
+    handle
+        .edit(ctx, CreateReply::default().content("Restarting..."))
+        .await?;
+
+    let reason = format!("`{}` restarted the bot", ctx.author().name);
+    let http = ctx.serenity_context().http.clone();
+    let shard_manager = ctx.framework().shard_manager.clone();
+    // The shutdown waits for running commands, including this one
+    tokio::spawn(async move {
+        utils::shutdown::shutdown(
+            &http,
+            &shard_manager,
+            &CONFIG,
+            ShutdownKind::Restart,
+            &reason,
+        )
+        .await;
+    });
+
+    Ok(())
+}
+
+pub static EXPORT: CommandsExport = &[restart];
@@ -2,7 +2,7 @@ use crate::CONFIG;
 use utils::shared_types::{CommandsExport, Context, Error};
 
 async fn check(ctx: Context<'_>) -> Result<bool, Error> {
-    Ok(utils::check(CONFIG.commands.say, &ctx, &CONFIG.logger).await)
+    Ok(utils::check(|commands| &commands.say, &ctx, &CONFIG).await)
 }
 
 /// Make the bot send a message.
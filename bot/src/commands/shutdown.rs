@@ -1,31 +1,32 @@
 use crate::CONFIG;
-use poise::{
-    CreateReply,
-    serenity_prelude::{CreateMessage, OnlineStatus, builder::CreateEmbed},
-};
+use poise::CreateReply;
+use utils::confirm::{Confirmation, confirm};
 use utils::shared_types::{CommandsExport, Context, Error};
+use utils::shutdown::ShutdownKind;
 
 #[poise::command(slash_command, guild_only, owners_only)]
 pub async fn shutdown(ctx: Context<'_>) -> Result<(), Error> {
-    let reply = CreateReply::default()
-        .content("Shutting down...")
-        .ephemeral(true);
-    ctx.send(reply.clone()).await?;
-
-    let author: String = ctx.author().name.to_owned();
-    let embed = CreateEmbed::new()
-        .title("Bot offline!")
-        .description(format!("`{}` shut down the bot", author))
-        .color(0xFF0000);
-    let message = CreateMessage::default().embed(embed);
-    CONFIG
-        .shutdown_channel
-        .send_message(&ctx.http(), message)
+    let Confirmation::Confirmed(handle) = confirm(ctx, "Shut down the bot?").await? else {
+        return Ok(());
+    };
+    handle
+        .edit(ctx, CreateReply::default().content("Shutting down..."))
         .await?;
 
-    ctx.serenity_context()
-        .set_presence(None, OnlineStatus::Invisible);
-    ctx.framework().shard_manager.shutdown_all().await;
+    let reason = format!("`{}` shut down the bot", ctx.author().name);
+    let http = ctx.serenity_context().http.clone();
+    let shard_manager = ctx.framework().shard_manager.clone();
+    // The shutdown waits for running commands, including this one
+    tokio::spawn(async move {
+        utils::shutdown::shutdown(
+            &http,
+            &shard_manager,
+            &CONFIG,
+            ShutdownKind::Shutdown,
+            &reason,
+        )
+        .await;
+    });
 
     Ok(())
 }
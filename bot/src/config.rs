@@ -0,0 +1,3 @@
+//! Re-exports the global configuration from the `utils` crate.
+
+pub use utils::config::CONFIG;
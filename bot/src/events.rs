@@ -15,16 +15,18 @@ impl serenity::EventHandler for EventHandler {
             .description("Syncing application commands...")
             .color(0x00FF00);
 
-        CONFIG
-            .ready_event_channel
-            .send_message(&ctx.http, CreateMessage::new().embed(embed))
-            .await
-            .map_err(|e| {
-                CONFIG
-                    .logger
-                    .error(&format!("Failed to send ready message: {}", e));
-            })
-            .ok();
+        for guild in CONFIG.settings().guilds.values() {
+            guild
+                .ready_event_channel
+                .send_message(&ctx.http, CreateMessage::new().embed(embed.clone()))
+                .await
+                .map_err(|e| {
+                    CONFIG
+                        .logger
+                        .error(&format!("Failed to send ready message: {}", e));
+                })
+                .ok();
+        }
 
         CONFIG
             .logger
@@ -6,55 +6,53 @@ mod config;
 
 pub use crate::config::CONFIG;
 
+mod cli;
 mod commands;
 mod events;
 
+use clap::Parser;
+use cli::{Cli, Command};
+use std::process::ExitCode;
+use utils::shutdown::{RESTART_EXIT_CODE, ShutdownKind};
+
 #[tokio::main]
-async fn main() {
-    let args: Vec<String> = std::env::args().collect();
-
-    let mut i = 1;
-    while i < args.len() {
-        // Safe to unwrap because of the loop condition
-        let arg = args.get(i).unwrap().as_str();
-        match arg {
-            "--clear-logs" | "-C" => {
-                for entry in std::fs::read_dir(&CONFIG.log_dir).unwrap() {
-                    let entry = match entry {
-                        Ok(e) => e,
-                        Err(e) => {
-                            CONFIG
-                                .logger
-                                .error(&format!("Error reading log directory: {}", e));
-                            continue;
-                        }
-                    };
-                    let path = entry.path();
-                    if path.is_file() {
-                        match std::fs::remove_file(path) {
-                            Ok(_) => {}
-                            Err(_) => {
-                                CONFIG.logger.error(&format!(
-                                    "Error deleting log file: {}",
-                                    entry.path().display()
-                                ));
-                                std::process::exit(1);
-                            }
-                        };
-                    }
-                }
-            }
-            _ => CONFIG.logger.error(&format!("Unknown argument: '{}'", arg)),
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    // The config path has to be known before `CONFIG` is accessed for the first time
+    if let Some(path) = &cli.config {
+        utils::config::set_config_path(path);
+    }
+
+    let command = cli.command.unwrap_or_default();
+    let uses_config = command.uses_config();
+    if uses_config {
+        cli::apply_log_levels(cli.log_level.as_deref());
+
+        // Route the logs of serenity and poise into our logger
+        if let Err(e) = utils::logging::bridge::install(&CONFIG.logger) {
+            CONFIG.logger.warn(&e.to_string());
         }
-        i += 1;
     }
 
-    let framework = utils::get_framework(
-        commands::all(),
-        CONFIG.guild_id,
-        CONFIG.commands_synced_channel,
-    )
-    .await;
+    let code = match command {
+        Command::Run => run().await,
+        Command::CheckConfig => cli::check_config(),
+        Command::SyncCommands { dry_run } => cli::sync_commands(dry_run).await,
+        Command::ListCommands => cli::list_commands(),
+        Command::ClearLogs { older_than } => cli::clear_logs(older_than.as_deref()),
+    };
+
+    if uses_config {
+        // Write all queued log messages before the process exits
+        CONFIG.logger.flush();
+    }
+    code
+}
+
+/// Runs the bot until the client stops.
+async fn run() -> ExitCode {
+    let framework = utils::get_framework(commands::all(), &CONFIG).await;
 
     let client = utils::get_client(
         framework,
@@ -69,10 +67,67 @@ async fn main() {
             CONFIG
                 .logger
                 .error(&format!("Error creating client: {}", e));
-            return;
+            return ExitCode::FAILURE;
         }
         Ok(c) => c,
     };
 
-    client.start().await.unwrap();
+    tokio::spawn(utils::watch_config(client.http.clone(), &CONFIG));
+
+    let http = client.http.clone();
+    let shard_manager = client.shard_manager.clone();
+    let result = tokio::select! {
+        result = client.start() => result,
+        signal = shutdown_signal() => {
+            let reason = format!("Received {}", signal);
+            utils::shutdown::shutdown(
+                &http,
+                &shard_manager,
+                &CONFIG,
+                ShutdownKind::Shutdown,
+                &reason,
+            )
+            .await;
+            // The client does not return by itself if no shard is connected
+            Ok(())
+        }
+    };
+
+    if let Err(e) = result {
+        CONFIG.logger.error(&format!("Client error: {}", e));
+        return ExitCode::FAILURE;
+    }
+
+    if utils::shutdown::is_restarting() {
+        return ExitCode::from(RESTART_EXIT_CODE);
+    }
+    ExitCode::SUCCESS
+}
+
+/// Waits for SIGTERM or SIGINT (Ctrl-C) and returns the name of the signal.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => return "SIGTERM",
+                    _ = tokio::signal::ctrl_c() => return "SIGINT",
+                }
+            }
+            Err(e) => CONFIG
+                .logger
+                .warn(&format!("Could not listen for SIGTERM: {}", e)),
+        }
+    }
+
+    if let Err(e) = tokio::signal::ctrl_c().await {
+        CONFIG
+            .logger
+            .warn(&format!("Could not listen for SIGINT: {}", e));
+        std::future::pending::<()>().await;
+    }
+    "SIGINT"
 }
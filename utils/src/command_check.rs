@@ -1,63 +1,229 @@
-use crate::shared_types::Context;
+use crate::config::{CommandRules, CommandsConfig, Config};
+use crate::cooldown::{Invocation, format_remaining};
+use crate::shared_types::{Context, Error};
 use poise::{
     CreateReply,
-    serenity_prelude::{CacheHttp, GuildId, RoleId},
+    serenity_prelude::{Permissions, RoleId},
 };
+use std::time::Instant;
 
-/// Checks if the user is allowed to invoke the command based on the given rules and context.
+/// Rejects commands that are not in the `enabled_commands` of the invoking guild. Used as part of
+/// the global command check, so it covers every command and reloaded settings apply immediately.
+pub(crate) async fn check_enabled(ctx: Context<'_>, config: &Config) -> Result<bool, Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Ok(true);
+    };
+    // Subcommands are enabled with their top-level command
+    let name = ctx
+        .parent_commands()
+        .first()
+        .map_or(&ctx.command().name, |root| &root.name);
+    let enabled = config
+        .settings()
+        .guild(guild_id)
+        .is_none_or(|guild| guild.is_enabled(name));
+    if enabled {
+        return Ok(true);
+    }
+
+    let reply = CreateReply::default()
+        .content(":x: This command is disabled in this server!")
+        .ephemeral(true);
+    ctx.send(reply).await.ok();
+    Ok(false)
+}
+
+/// Checks if the user is allowed to invoke the command based on the rules of the invoking guild.
+///
+/// `rules` selects the rules of the command from the guild's [`CommandsConfig`]. The rules are
+/// looked up on every call, so reloaded settings apply immediately.
 pub async fn check(
-    command_rules: crate::config::CommandRules,
+    rules: fn(&CommandsConfig) -> &CommandRules,
     ctx: &Context<'_>,
-    logger: &crate::logging::Logger,
+    config: &Config,
 ) -> bool {
     let Some(guild_id) = ctx.guild_id() else {
-        logger.error("command check called with non-guild context");
+        config
+            .logger
+            .error("command check called with non-guild context");
         return false;
     };
 
-    if let Some(roles) = command_rules.roles
-        && !check_roles(roles, ctx, guild_id).await
+    let settings = config.settings();
+    let Some(guild) = settings.guild(guild_id) else {
+        config.logger.warn(&format!(
+            "command '{}' used in unconfigured guild {}",
+            ctx.command().name,
+            guild_id
+        ));
+        let reply = CreateReply::default()
+            .content(":x: This server is not configured for this bot!")
+            .ephemeral(true);
+        ctx.send(reply).await.ok();
+        return false;
+    };
+
+    let command_rules = rules(&guild.commands);
+    let author_id = ctx.author().id;
+
+    if command_rules.denied_users.contains(&author_id) {
+        let reply = CreateReply::default()
+            .content(":x: You are not allowed to use this command!")
+            .ephemeral(true);
+        ctx.send(reply).await.ok();
+        return false;
+    }
+
+    if !command_rules.allowed_users.contains(&author_id)
+        && command_rules.has_member_rules()
+        && !check_member(command_rules, ctx, config).await
     {
         return false;
     }
 
-    if let Some(channels) = command_rules.channels
+    if let Some(channels) = &command_rules.channels
         && !check_channels(channels, command_rules.channel_whitelist, ctx).await
     {
         return false;
     }
 
+    if command_rules.cooldown.is_enabled() && !check_cooldown(command_rules, ctx).await {
+        return false;
+    }
+
     true
 }
 
-async fn check_roles(roles: Vec<RoleId>, ctx: &Context<'_>, guild_id: GuildId) -> bool {
-    let mut has_role = false;
-    for role in &roles {
-        let result = ctx
-            .author()
-            .has_role(ctx.http(), guild_id, role)
-            .await
-            .unwrap_or(false);
-
-        if result {
-            has_role = true;
-            break;
-        }
+/// The roles and permissions of the invoking member.
+struct MemberInfo {
+    roles: Vec<RoleId>,
+    /// The position of the highest role, 0 if the member only has `@everyone`
+    highest_position: u16,
+    /// The permissions in the invoking channel, if known
+    permissions: Option<Permissions>,
+}
+
+impl MemberInfo {
+    /// Collects the data from the interaction or the cache, only falling back to a request if the
+    /// member is not cached.
+    async fn load(ctx: &Context<'_>) -> Option<Self> {
+        let member = ctx.author_member().await?;
+
+        let guild = ctx.guild()?;
+        let highest_position = member
+            .roles
+            .iter()
+            .filter_map(|role| guild.roles.get(role))
+            .map(|role| role.position)
+            .max()
+            .unwrap_or(0);
+        // Interactions include the permissions in the channel, including overwrites
+        let permissions = member.permissions.or_else(|| {
+            let channel_id = ctx.channel_id();
+            let channel = guild.channels.get(&channel_id).or_else(|| {
+                // Threads use the permissions of their parent channel
+                let thread = guild
+                    .threads
+                    .iter()
+                    .find(|thread| thread.id == channel_id)?;
+                guild.channels.get(&thread.parent_id?)
+            })?;
+            Some(guild.user_permissions_in(channel, &member))
+        });
+
+        Some(Self {
+            roles: member.roles.clone(),
+            highest_position,
+            permissions,
+        })
     }
+}
 
-    if !has_role {
+/// Checks the role and permission rules, which administrators skip with `admin_bypass`.
+async fn check_member(rules: &CommandRules, ctx: &Context<'_>, config: &Config) -> bool {
+    let Some(member) = MemberInfo::load(ctx).await else {
+        config.logger.warn(&format!(
+            "could not get the roles of {} for command '{}'",
+            ctx.author().name,
+            ctx.command().name
+        ));
         let reply = CreateReply::default()
-            .content(":x: You do not have permission to use this command!")
+            .content(":x: Could not check your roles, try again later!")
             .ephemeral(true);
-
         ctx.send(reply).await.ok();
+        return false;
+    };
+
+    let permissions = member.permissions.unwrap_or_default();
+    if rules.admin_bypass && permissions.administrator() {
+        return true;
     }
 
-    has_role
+    let has_roles = rules
+        .roles
+        .as_ref()
+        .is_none_or(|roles| roles.iter().any(|role| member.roles.contains(role)))
+        && rules
+            .all_roles
+            .as_ref()
+            .is_none_or(|roles| roles.iter().all(|role| member.roles.contains(role)))
+        && rules
+            .min_role_position
+            .is_none_or(|position| member.highest_position >= position);
+
+    let content = if !has_roles {
+        String::from(":x: You do not have permission to use this command!")
+    } else if let Some(required) = rules.permissions
+        && !permissions.contains(required)
+    {
+        format!(
+            ":x: You need the {} permission to use this command!",
+            required - permissions
+        )
+    } else {
+        return true;
+    };
+
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    ctx.send(reply).await.ok();
+    false
+}
+
+/// Checks the cooldowns of the command and starts them if none is running.
+async fn check_cooldown(rules: &CommandRules, ctx: &Context<'_>) -> bool {
+    let member = if rules.cooldown.bypass_roles.is_empty() {
+        None
+    } else {
+        MemberInfo::load(ctx).await
+    };
+
+    let invocation = Invocation {
+        user_id: ctx.author().id,
+        channel_id: ctx.channel_id(),
+        guild_id: ctx.guild_id(),
+        roles: member.as_ref().map_or(&[], |member| &member.roles),
+    };
+    let Some(remaining) = ctx.data().cooldowns.check(
+        &ctx.command().name,
+        &rules.cooldown,
+        &invocation,
+        Instant::now(),
+    ) else {
+        return true;
+    };
+
+    let reply = CreateReply::default()
+        .content(format!(
+            ":hourglass: This command is on cooldown, try again in {}!",
+            format_remaining(remaining)
+        ))
+        .ephemeral(true);
+    ctx.send(reply).await.ok();
+    false
 }
 
 async fn check_channels(
-    channels: Vec<poise::serenity_prelude::ChannelId>,
+    channels: &[poise::serenity_prelude::ChannelId],
     channel_whitelist: bool,
     ctx: &Context<'_>,
 ) -> bool {
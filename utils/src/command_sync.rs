@@ -0,0 +1,259 @@
+//! Syncs the registered slash commands with the commands of the bot.
+
+use crate::config::GuildConfig;
+use crate::shared_types::{Data, Error};
+use poise::serenity_prelude::{Command, GuildId, Http};
+use serde_json::Value;
+use std::fmt;
+
+/// The keys of a command that are compared to decide whether it needs to be updated.
+const COMPARED_KEYS: [&str; 5] = [
+    "name",
+    "description",
+    "options",
+    "name_localizations",
+    "description_localizations",
+];
+
+/// What happened to each command during a sync.
+#[derive(Debug, Default)]
+pub struct SyncSummary {
+    /// Commands that were registered
+    pub created: Vec<String>,
+    /// Commands that were changed
+    pub updated: Vec<String>,
+    /// Commands that were removed
+    pub deleted: Vec<String>,
+    /// Commands that were already up to date
+    pub unchanged: Vec<String>,
+}
+
+impl SyncSummary {
+    /// Formats the summary as Discord markdown, one line per non-empty category.
+    pub fn describe(&self) -> String {
+        let mut output = String::new();
+        for (label, names) in self.categories() {
+            let names: Vec<String> = names.iter().map(|name| format!("`{}`", name)).collect();
+            output.push_str(&format!("**{}:** {}\n", label, names.join(", ")));
+        }
+        output
+    }
+
+    fn categories(&self) -> impl Iterator<Item = (&'static str, &Vec<String>)> {
+        [
+            ("Created", &self.created),
+            ("Updated", &self.updated),
+            ("Deleted", &self.deleted),
+            ("Unchanged", &self.unchanged),
+        ]
+        .into_iter()
+        .filter(|(_, names)| !names.is_empty())
+    }
+}
+
+/// Formats the summary as plain text for the terminal, one line per non-empty category.
+impl fmt::Display for SyncSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (label, names) in self.categories() {
+            writeln!(f, "{}: {}", label, names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Brings the registered guild commands in line with the `commands` enabled in `guild`.
+///
+/// Only commands that are new, changed or no longer exist are sent to Discord, so command IDs and
+/// permission overrides of unchanged commands are kept. With `dry_run`, nothing is sent and the
+/// summary describes what would happen.
+pub async fn sync_commands(
+    http: &Http,
+    guild_id: GuildId,
+    commands: &[poise::Command<Data, Error>],
+    guild: &GuildConfig,
+    dry_run: bool,
+) -> Result<SyncSummary, Error> {
+    let mut summary = SyncSummary::default();
+    let mut registered = guild_id.get_commands_with_localizations(http).await?;
+
+    for create in poise::builtins::create_application_commands(commands) {
+        let wanted = normalize(serde_json::to_value(&create)?);
+        let name = wanted["name"].as_str().unwrap_or_default().to_string();
+        if !guild.is_enabled(&name) {
+            continue;
+        }
+
+        let existing = registered
+            .iter()
+            .position(|command| command.name == name)
+            .map(|i| registered.swap_remove(i));
+
+        match existing {
+            None => {
+                if !dry_run {
+                    guild_id.create_command(http, create).await?;
+                }
+                summary.created.push(name);
+            }
+            Some(command) if normalize(serde_json::to_value(&command)?) != wanted => {
+                if !dry_run {
+                    guild_id.edit_command(http, command.id, create).await?;
+                }
+                summary.updated.push(name);
+            }
+            Some(_) => summary.unchanged.push(name),
+        }
+    }
+
+    for command in registered {
+        if !dry_run {
+            guild_id.delete_command(http, command.id).await?;
+        }
+        summary.deleted.push(command.name);
+    }
+
+    Ok(summary)
+}
+
+/// Removes all global commands, as the bot only registers guild commands. Called once before
+/// syncing the guilds. Returns the names of the removed commands, with `dry_run` the names of the
+/// commands that would be removed.
+pub async fn delete_global_commands(http: &Http, dry_run: bool) -> Result<Vec<String>, Error> {
+    let mut deleted = Vec::new();
+    for command in Command::get_global_commands(http).await? {
+        if !dry_run {
+            Command::delete_global_command(http, command.id).await?;
+        }
+        deleted.push(command.name);
+    }
+    Ok(deleted)
+}
+
+/// Keeps only the compared keys of a serialized command and removes values that Discord treats
+/// as absent (`null`, `false`, empty lists and maps), so registered and local commands can be
+/// compared directly.
+fn normalize(command: Value) -> Value {
+    let Value::Object(mut map) = command else {
+        return command;
+    };
+    map.retain(|key, _| COMPARED_KEYS.contains(&key.as_str()));
+    strip_empty(Value::Object(map))
+}
+
+fn strip_empty(value: Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (key, strip_empty(value)))
+                .filter(|(_, value)| !is_empty(value))
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(strip_empty).collect()),
+        value => value,
+    }
+}
+
+fn is_empty(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => true,
+        Value::Array(values) => values.is_empty(),
+        Value::Object(map) => map.is_empty(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// A command as returned by Discord, with IDs and defaults filled in
+    fn registered() -> Value {
+        json!({
+            "id": "1",
+            "application_id": "2",
+            "guild_id": "3",
+            "version": "4",
+            "type": 1,
+            "name": "purge",
+            "description": "Deletes messages",
+            "name_localizations": null,
+            "description_localizations": {},
+            "nsfw": false,
+            "default_member_permissions": null,
+            "options": [{
+                "type": 4,
+                "name": "amount",
+                "description": "The amount",
+                "required": false,
+                "autocomplete": false,
+                "choices": [],
+                "min_value": 1
+            }]
+        })
+    }
+
+    /// The same command as created by the bot
+    fn local() -> Value {
+        json!({
+            "type": 1,
+            "name": "purge",
+            "description": "Deletes messages",
+            "options": [{
+                "type": 4,
+                "name": "amount",
+                "description": "The amount",
+                "min_value": 1
+            }]
+        })
+    }
+
+    #[test]
+    fn normalize_keeps_only_compared_keys() {
+        let normalized = normalize(registered());
+        let keys: Vec<&String> = normalized.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["name", "description", "options"]);
+    }
+
+    #[test]
+    fn normalize_strips_absent_values() {
+        assert_eq!(
+            normalize(registered())["options"][0],
+            json!({
+                "type": 4,
+                "name": "amount",
+                "description": "The amount",
+                "min_value": 1
+            })
+        );
+    }
+
+    #[test]
+    fn unchanged_commands_are_equal() {
+        assert_eq!(normalize(registered()), normalize(local()));
+    }
+
+    #[test]
+    fn changed_commands_differ() {
+        let mut description = local();
+        description["description"] = json!("Deletes many messages");
+        assert_ne!(normalize(registered()), normalize(description));
+
+        let mut option = local();
+        option["options"][0]["required"] = json!(true);
+        assert_ne!(normalize(registered()), normalize(option));
+
+        let mut localized = local();
+        localized["name_localizations"] = json!({ "de": "loeschen" });
+        assert_ne!(normalize(registered()), normalize(localized));
+    }
+
+    #[test]
+    fn ignored_keys_do_not_matter() {
+        let mut other = registered();
+        other["id"] = json!("5");
+        other["version"] = json!("6");
+        other["default_member_permissions"] = json!("8");
+        assert_eq!(normalize(registered()), normalize(other));
+    }
+}
@@ -1,31 +1,165 @@
 //! Contains the Settings
+//!
+//! The settings are read from a TOML file. The path is taken from the `--config` CLI flag (see
+//! [`set_config_path`]), the `CONFIG_PATH` environment variable or defaults to `config.toml`.
+//! Every key is optional and falls back to the default documented on the respective field.
+//!
+//! The [`Settings`] part of the config can be reloaded at runtime, see [`Config::reload`].
 
+use crate::logging::{
+    ColorSupport, DEFAULT_BUFFER_CAPACITY, FileFormat, LogLevel, Logger, LoggingFormat,
+    OverflowPolicy, RotationPolicy, TargetFilter, Template, TemplateError,
+};
 use dotenv::dotenv;
-use once_cell::sync::Lazy;
-use poise::serenity_prelude::{ChannelId, GatewayIntents, GuildId, RoleId};
-use crate::logging::Logger;
+use once_cell::sync::{Lazy, OnceCell};
+use poise::serenity_prelude::{ChannelId, GatewayIntents, GuildId, Permissions, RoleId, UserId};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
+
+/// The config file path used if neither the CLI flag nor the environment variable is set.
+pub const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+/// The environment variable that can hold the config file path.
+pub const CONFIG_PATH_ENV: &str = "CONFIG_PATH";
+
+/// The environment variable that can hold log level directives, applied on top of
+/// [`LoggingConfig::levels`].
+pub const LOG_LEVELS_ENV: &str = "LOG_LEVELS";
+
+static CONFIG_PATH: OnceCell<PathBuf> = OnceCell::new();
+
+/// Sets the config file path. Must be called before [`CONFIG`] is first accessed, otherwise it
+/// has no effect. Returns `false` if the path was already set.
+pub fn set_config_path(path: impl Into<PathBuf>) -> bool {
+    CONFIG_PATH.set(path.into()).is_ok()
+}
+
+/// Returns the path of the config file and whether it has to exist.
+///
+/// The path set with [`set_config_path`] and the `CONFIG_PATH` environment variable (including
+/// the .env file) have to exist, the default [`DEFAULT_CONFIG_PATH`] may be missing.
+pub fn config_path() -> (PathBuf, bool) {
+    dotenv().ok();
+
+    match CONFIG_PATH.get() {
+        Some(path) => (path.clone(), true),
+        None => match std::env::var(CONFIG_PATH_ENV) {
+            Ok(path) => (PathBuf::from(path), true),
+            Err(_) => (PathBuf::from(DEFAULT_CONFIG_PATH), false),
+        },
+    }
+}
 
 /// Rules for a specific command
-#[derive(Debug)]
+///
+/// A user has to pass every set rule. Users in `allowed_users` and, with `admin_bypass`,
+/// administrators skip the role and permission rules, but not the channel rules.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CommandRules {
-    /// Roles needed to use the command. If None, no role restriction.
+    /// Roles needed to use the command, any of them is enough. If None, no role restriction.
     pub roles: Option<Vec<RoleId>>,
+    /// Roles needed to use the command, all of them are required. If None, no restriction.
+    pub all_roles: Option<Vec<RoleId>>,
+    /// The position the highest role of the user must have at least. If None, no restriction.
+    pub min_role_position: Option<u16>,
+    /// Discord permissions the user needs in the channel, like `["MANAGE_MESSAGES"]`. If None, no
+    /// restriction.
+    #[serde(deserialize_with = "deserialize_permissions")]
+    pub permissions: Option<Permissions>,
+    /// Users that can always use the command, regardless of roles and permissions
+    pub allowed_users: Vec<UserId>,
+    /// Users that can never use the command
+    pub denied_users: Vec<UserId>,
+    /// Whether users with the administrator permission skip the role and permission rules
+    pub admin_bypass: bool,
     /// Channels where the command can/cannot be used. If None, no channel restriction.
     pub channels: Option<Vec<ChannelId>>,
     /// Whether the `channels` list is a whitelist (true) or blacklist (false)
     pub channel_whitelist: bool,
+    /// How often the command can be used
+    pub cooldown: CooldownConfig,
+}
+
+/// Cooldowns of a command. Every cooldown that applies to an invocation has to be over. Only
+/// invocations that pass all other rules start the cooldowns.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CooldownConfig {
+    /// Seconds a user has to wait between uses. `0` disables the cooldown.
+    pub user_secs: u64,
+    /// Seconds between uses in the same channel. `0` disables the cooldown.
+    pub channel_secs: u64,
+    /// Seconds between uses in the same guild. `0` disables the cooldown.
+    pub guild_secs: u64,
+    /// Seconds between uses in all guilds together. `0` disables the cooldown.
+    pub global_secs: u64,
+    /// Users with any of these roles are not affected by the cooldowns
+    pub bypass_roles: Vec<RoleId>,
+}
+
+impl CooldownConfig {
+    /// Whether any cooldown is set.
+    pub fn is_enabled(&self) -> bool {
+        self.user_secs > 0 || self.channel_secs > 0 || self.guild_secs > 0 || self.global_secs > 0
+    }
+}
+
+impl CommandRules {
+    /// Whether any rule needs the roles or permissions of the user.
+    pub fn has_member_rules(&self) -> bool {
+        self.roles.is_some()
+            || self.all_roles.is_some()
+            || self.min_role_position.is_some()
+            || self.permissions.is_some()
+    }
+}
+
+/// Deserializes a list of permission names like `MANAGE_MESSAGES`.
+fn deserialize_permissions<'de, D>(deserializer: D) -> Result<Option<Permissions>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let Some(names) = Option::<Vec<String>>::deserialize(deserializer)? else {
+        return Ok(None);
+    };
+
+    let mut permissions = Permissions::empty();
+    for name in names {
+        let permission = Permissions::from_name(&name.to_uppercase())
+            .ok_or_else(|| serde::de::Error::custom(format!("unknown permission '{}'", name)))?;
+        permissions |= permission;
+    }
+    Ok(Some(permissions))
 }
 
 /// Configuration for all commands
-#[derive(Debug)]
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
 pub struct CommandsConfig {
     /// Configuration for the avatar command
+    ///
+    /// Default: roles `[1233889604436754525]`
     pub avatar: CommandRules,
     /// Configuration for the embed command
+    ///
+    /// Default: roles `[1237741325462405223]`
     pub embed: CommandRules,
+    /// Configuration for the ping command
+    ///
+    /// Default: no restrictions
+    pub ping: CommandRules,
     /// Configuration for the purge command
+    ///
+    /// Default: roles `[1234229041343762513]`, permissions `["MANAGE_MESSAGES"]`
     pub purge: CommandRules,
     /// Configuration for the say command
+    ///
+    /// Default: roles `[1053019464075063327]`
     pub say: CommandRules,
 }
 
@@ -34,89 +168,96 @@ impl Default for CommandsConfig {
         Self {
             avatar: CommandRules {
                 roles: Some(vec![RoleId::new(1233889604436754525)]),
-                channels: None,
-                channel_whitelist: false,
+                ..Default::default()
             },
             embed: CommandRules {
                 roles: Some(vec![RoleId::new(1237741325462405223)]),
-                channels: None,
-                channel_whitelist: false,
+                ..Default::default()
             },
+            ping: CommandRules::default(),
             purge: CommandRules {
                 roles: Some(vec![RoleId::new(1234229041343762513)]),
-                channels: None,
-                channel_whitelist: false,
+                permissions: Some(Permissions::MANAGE_MESSAGES),
+                ..Default::default()
             },
             say: CommandRules {
                 roles: Some(vec![RoleId::new(1053019464075063327)]),
-                channels: None,
-                channel_whitelist: false,
+                ..Default::default()
             },
         }
     }
 }
 
-/// The main configuration struct
-#[derive(Debug)]
-pub struct Config {
-    // ┌───────────────────────┐
-    // │ General Configuration │
-    // └───────────────────────┘
-    /// The directory where log files are stored
-    pub log_dir: String,
-    /// The logger instance
-    pub logger: Logger,
-    /// The Discord bot token
-    pub token: String,
-    /// The guild ID the bot operates in
-    pub guild_id: GuildId,
-    /// The gateway intents the bot uses
-    pub intents: GatewayIntents,
+/// Configuration for a single guild
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GuildConfig {
     /// Configuration for commands
     pub commands: CommandsConfig,
+    /// Names of the commands registered in this guild. If None, all commands are registered.
+    ///
+    /// Changes are enforced immediately, but the registered commands are only updated on restart.
+    pub enabled_commands: Option<Vec<String>>,
+    /// The maximum amount of messages a single purge may delete
+    ///
+    /// Default: `1000`
+    pub purge_max_amount: u32,
+    /// The maximum amount of messages a single purge scans for messages matching its filters
+    ///
+    /// Default: `5000`
+    pub purge_max_scan: u32,
+    /// Purges of more than this amount of messages have to be confirmed first
+    ///
+    /// Default: `100`
+    pub purge_confirm_threshold: u32,
 
     // ┌────────────────────┐
     // │ Event Log Channels │
     // └────────────────────┘
     /// Channel for ready events
+    ///
+    /// Default: `1239935861370650634`
     pub ready_event_channel: ChannelId,
 
     // ┌──────────────────────┐
     // │ Command Log Channels │
     // └──────────────────────┘
     /// Channel for purge command logs
+    ///
+    /// Default: `1239387297003077682`
     pub purge_command_channel: ChannelId,
 
     // ┌───────────────────┐
     // │ Misc Log Channels │
     // └───────────────────┘
     /// Channel for command sync logs
+    ///
+    /// Default: `1239935861370650634`
     pub commands_synced_channel: ChannelId,
     /// Channel for shutdown logs
+    ///
+    /// Default: `1239935861370650634`
     pub shutdown_channel: ChannelId,
+    /// Channel for config reload logs
+    ///
+    /// Default: `1239935861370650634`
+    pub config_reload_channel: ChannelId,
+    /// Channel for unexpected errors
+    ///
+    /// Default: `1239935861370650634`
+    pub error_log_channel: ChannelId,
 }
 
-impl Config {
-    fn new() -> Self {
-        dotenv().ok();
-
+impl Default for GuildConfig {
+    fn default() -> Self {
         let bot_status_channel = ChannelId::new(1239935861370650634);
 
-        let log_dir = String::from("logs");
-
-        let now = chrono::Utc::now().with_timezone(&chrono_tz::Europe::Berlin);
-        let datetime = now.format("%Y-%m-%d_%H-%M-%S_%Z").to_string();
-        let logger = Logger::builder()
-            .output_file(format!("{}/{}.log", log_dir, datetime))
-            .build();
-
         Self {
-            log_dir,
-            logger,
-            token: std::env::var("DISCORD_TOKEN").expect("Missing token in .env file"),
-            guild_id: GuildId::new(1018921751691923536),
-            intents: GatewayIntents::all(),
             commands: Default::default(),
+            enabled_commands: None,
+            purge_max_amount: 1000,
+            purge_max_scan: 5000,
+            purge_confirm_threshold: 100,
 
             // Event Log Channels
             ready_event_channel: bot_status_channel,
@@ -127,6 +268,375 @@ impl Config {
             // Misc Log Channels
             commands_synced_channel: bot_status_channel,
             shutdown_channel: bot_status_channel,
+            config_reload_channel: bot_status_channel,
+            error_log_channel: bot_status_channel,
+        }
+    }
+}
+
+impl GuildConfig {
+    /// Whether the command with the given name is enabled in this guild.
+    pub fn is_enabled(&self, command: &str) -> bool {
+        self.enabled_commands
+            .as_ref()
+            .is_none_or(|enabled| enabled.iter().any(|name| name == command))
+    }
+}
+
+/// Configuration of the logger and its log file. Changes require a restart.
+///
+/// The log file is rotated when it would exceed `max_file_size_mb` or when the day changes.
+/// Rotated files are renamed to `<name>.<timestamp>.log` and compressed if `compress` is set.
+#[derive(Debug, Clone, Eq, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LoggingConfig {
+    /// The name of the active log file in [`Config::log_dir`]
+    ///
+    /// Default: `"bot.log"`
+    pub file_name: String,
+    /// The format of the log file, either `"template"` (same as the terminal without colors) or
+    /// `"json"` (one JSON object per line)
+    ///
+    /// Default: `"template"`
+    pub file_format: FileFormat,
+    /// The template of log messages in the terminal and in template log files. For the
+    /// placeholders and formatting, see [`LoggingFormat`].
+    ///
+    /// Default: `"*[{{timestampc}}] [{{levelc}}]* {{messagec}}"`
+    pub template: String,
+    /// The colors used in the terminal: `"none"`, `"16"`, `"256"` or `"truecolor"`. If None, it is
+    /// detected from the terminal and the `NO_COLOR`, `TERM` and `COLORTERM` environment variables.
+    pub color: Option<ColorSupport>,
+    /// The size in megabytes at which the log file is rotated. `0` disables size based rotation.
+    ///
+    /// Default: `10`
+    pub max_file_size_mb: u64,
+    /// Whether the log file is rotated every day
+    ///
+    /// Default: `true`
+    pub rotate_daily: bool,
+    /// Whether rotated files are compressed with gzip
+    ///
+    /// Default: `true`
+    pub compress: bool,
+    /// The number of days rotated files are kept. `0` keeps them forever.
+    ///
+    /// Default: `30`
+    pub max_age_days: u64,
+    /// The maximum size of all log files together in megabytes. The oldest rotated files are
+    /// deleted first. `0` disables the limit.
+    ///
+    /// Default: `500`
+    pub max_total_size_mb: u64,
+    /// The number of messages buffered for the background log writer
+    ///
+    /// Default: `1024`
+    pub buffer_capacity: usize,
+    /// What happens to messages when the buffer is full: `"drop"` discards them, `"block"` waits
+    /// for the writer and `"report"` discards them and logs how many were dropped
+    ///
+    /// Default: `"report"`
+    pub overflow_policy: OverflowPolicy,
+    /// Channel that log messages of at least `discord_min_level` are posted to, batched into
+    /// embeds. If None, logs are not posted to Discord.
+    pub discord_channel: Option<ChannelId>,
+    /// The minimum level of messages posted to `discord_channel`
+    ///
+    /// Default: `"warn"`
+    pub discord_min_level: LogLevel,
+    /// Log levels as comma separated `target=level` directives, where the target is a module path
+    /// like `commands::purge` or `serenity::gateway`. A level without a target applies to all
+    /// other targets of the bot. Extended by the `LOG_LEVELS` environment variable and adjustable
+    /// at runtime with `/loglevel`.
+    ///
+    /// Default: `"info"`
+    pub levels: TargetFilter,
+    /// The log level of dependencies like serenity and poise, unless set in `levels`
+    ///
+    /// Default: `"warn"`
+    pub dependency_level: LogLevel,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        Self {
+            file_name: String::from("bot.log"),
+            file_format: FileFormat::Template,
+            template: LoggingFormat::default().template,
+            color: None,
+            max_file_size_mb: 10,
+            rotate_daily: true,
+            compress: true,
+            max_age_days: 30,
+            max_total_size_mb: 500,
+            buffer_capacity: DEFAULT_BUFFER_CAPACITY,
+            overflow_policy: OverflowPolicy::Report,
+            discord_channel: None,
+            discord_min_level: LogLevel::Warn,
+            levels: TargetFilter::new(LogLevel::Info),
+            dependency_level: LogLevel::Warn,
+        }
+    }
+}
+
+impl LoggingConfig {
+    /// Returns the rotation policy described by this config.
+    pub fn rotation_policy(&self) -> RotationPolicy {
+        const MEGABYTE: u64 = 1024 * 1024;
+        let non_zero = |value: u64| (value > 0).then_some(value);
+
+        RotationPolicy {
+            max_size: non_zero(self.max_file_size_mb).map(|mb| mb * MEGABYTE),
+            daily: self.rotate_daily,
+            compress: self.compress,
+            max_age: non_zero(self.max_age_days)
+                .map(|days| Duration::from_secs(days * 24 * 60 * 60)),
+            max_total_size: non_zero(self.max_total_size_mb).map(|mb| mb * MEGABYTE),
+        }
+    }
+}
+
+/// Settings that are re-read when the config file is reloaded
+#[derive(Debug)]
+pub struct Settings {
+    /// Configuration for each guild the bot operates in. Commands are only registered in these
+    /// guilds, adding or removing a guild requires a restart.
+    ///
+    /// Default: `1018921751691923536` with the [`GuildConfig`] defaults
+    pub guilds: HashMap<GuildId, GuildConfig>,
+    /// The reply to commands of non-owners while the bot is in maintenance mode
+    ///
+    /// Default: `":construction: The bot is under maintenance, try again later!"`
+    pub maintenance_message: String,
+}
+
+impl Settings {
+    /// Returns the configuration of the given guild, if it is configured.
+    pub fn guild(&self, guild_id: GuildId) -> Option<&GuildConfig> {
+        self.guilds.get(&guild_id)
+    }
+}
+
+/// The main configuration struct
+#[derive(Debug)]
+pub struct Config {
+    // ┌───────────────────────┐
+    // │ General Configuration │
+    // └───────────────────────┘
+    /// The directory where log files are stored. Changes require a restart.
+    ///
+    /// Default: `"logs"`
+    pub log_dir: String,
+    /// The path of the SQLite database. Changes require a restart.
+    ///
+    /// Default: `"data/bot.db"`
+    pub database_path: PathBuf,
+    /// Configuration of the log file and its rotation. Changes require a restart.
+    pub logging: LoggingConfig,
+    /// The logger instance. Not read from the config file.
+    pub logger: Logger,
+    /// The Discord bot token. Read from the `DISCORD_TOKEN` environment variable.
+    pub token: String,
+    /// The gateway intents the bot uses. Not read from the config file.
+    pub intents: GatewayIntents,
+    /// The path of the config file
+    pub path: PathBuf,
+
+    settings: RwLock<Arc<Settings>>,
+}
+
+/// Mirrors the keys of the config file. See [`Config`] and [`Settings`] for their documentation.
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ConfigFile {
+    log_dir: String,
+    database_path: PathBuf,
+    logging: LoggingConfig,
+    guilds: HashMap<GuildId, GuildConfig>,
+    maintenance_message: String,
+}
+
+impl Default for ConfigFile {
+    fn default() -> Self {
+        Self {
+            log_dir: String::from("logs"),
+            database_path: PathBuf::from("data/bot.db"),
+            logging: LoggingConfig::default(),
+            guilds: HashMap::from([(GuildId::new(1018921751691923536), GuildConfig::default())]),
+            maintenance_message: String::from(
+                ":construction: The bot is under maintenance, try again later!",
+            ),
+        }
+    }
+}
+
+impl ConfigFile {
+    fn read(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        match std::fs::read_to_string(path) {
+            Ok(content) => toml::from_str(&content)
+                .map_err(|e| ConfigError::Parse(path.to_path_buf(), Box::new(e))),
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(ConfigError::Read(path.to_path_buf(), e)),
+        }
+    }
+
+    /// Returns the log levels of the file with the `LOG_LEVELS` environment variable applied.
+    fn levels(&self) -> Result<TargetFilter, ConfigError> {
+        let mut levels = self.logging.levels.clone();
+        if let Ok(directives) = std::env::var(LOG_LEVELS_ENV) {
+            levels
+                .apply(&directives)
+                .map_err(|e| ConfigError::InvalidEnv(LOG_LEVELS_ENV, e.to_string()))?;
+        }
+        Ok(levels)
+    }
+
+    fn into_settings(self) -> Settings {
+        Settings {
+            guilds: self.guilds,
+            maintenance_message: self.maintenance_message,
+        }
+    }
+}
+
+/// Errors that can occur while loading the configuration.
+#[derive(Debug)]
+pub enum ConfigError {
+    /// The config file could not be read.
+    Read(PathBuf, std::io::Error),
+    /// The config file contains invalid TOML, an unknown key or a value of the wrong type.
+    Parse(PathBuf, Box<toml::de::Error>),
+    /// A required environment variable is not set.
+    MissingEnv(&'static str),
+    /// An environment variable has an invalid value.
+    InvalidEnv(&'static str, String),
+    /// The logging template is invalid.
+    Template(TemplateError),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Read(path, e) => write!(f, "Could not read '{}': {}", path.display(), e),
+            Self::Parse(path, e) => write!(f, "Invalid config in '{}':\n{}", path.display(), e),
+            Self::MissingEnv(var) => write!(f, "Missing '{}' in environment or .env file", var),
+            Self::InvalidEnv(var, e) => write!(f, "Invalid '{}' in environment: {}", var, e),
+            Self::Template(e) => write!(f, "Invalid logging template: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Loads the configuration from the given file and the environment.
+    ///
+    /// If `required` is false and the file does not exist, the defaults are used instead.
+    pub fn load(path: &Path, required: bool) -> Result<Self, ConfigError> {
+        dotenv().ok();
+
+        let file = ConfigFile::read(path, required)?;
+
+        let token =
+            std::env::var("DISCORD_TOKEN").map_err(|_| ConfigError::MissingEnv("DISCORD_TOKEN"))?;
+
+        let levels = file.levels()?;
+
+        let mut builder = Logger::builder();
+        if let Some(support) = file.logging.color {
+            builder = builder.color_support(support);
+        }
+        let logger = builder
+            .levels(levels)
+            .dependency_level(file.logging.dependency_level.clone())
+            .output_file(format!("{}/{}", file.log_dir, file.logging.file_name))
+            .rotation(file.logging.rotation_policy())
+            .buffer_capacity(file.logging.buffer_capacity)
+            .overflow_policy(file.logging.overflow_policy)
+            .format(LoggingFormat {
+                template: file.logging.template.clone(),
+                file_format: file.logging.file_format,
+                ..Default::default()
+            })
+            .build()
+            .map_err(ConfigError::Template)?;
+
+        Ok(Self {
+            log_dir: file.log_dir.clone(),
+            database_path: file.database_path.clone(),
+            logging: file.logging.clone(),
+            logger,
+            token,
+            intents: GatewayIntents::all(),
+            path: path.to_path_buf(),
+            settings: RwLock::new(Arc::new(file.into_settings())),
+        })
+    }
+
+    /// Reads and validates the given file and the `LOG_LEVELS` environment variable like
+    /// [`Config::load`], but without reading the token or creating a logger. Returns the settings
+    /// of the file.
+    ///
+    /// If `required` is false and the file does not exist, the defaults are used instead.
+    pub fn check(path: &Path, required: bool) -> Result<Settings, ConfigError> {
+        let file = ConfigFile::read(path, required)?;
+        file.levels()?;
+        Template::parse(&file.logging.template).map_err(ConfigError::Template)?;
+        Ok(file.into_settings())
+    }
+
+    /// Returns the current settings. The returned snapshot is not affected by later reloads.
+    pub fn settings(&self) -> Arc<Settings> {
+        self.settings
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Re-reads the config file and replaces the current [`Settings`].
+    ///
+    /// Keys outside of [`Settings`] are not applied, a warning is logged if they changed. On error
+    /// the current settings are kept.
+    pub fn reload(&self) -> Result<(), ConfigError> {
+        let file = ConfigFile::read(&self.path, true)?;
+
+        if file.log_dir != self.log_dir
+            || file.database_path != self.database_path
+            || file.logging != self.logging
+        {
+            self.logger.warn(
+                "Changes to 'log_dir', 'database_path' or 'logging' require a restart to take effect",
+            );
+        }
+
+        let settings = self.settings();
+        if file.guilds.len() != settings.guilds.len()
+            || file
+                .guilds
+                .keys()
+                .any(|id| !settings.guilds.contains_key(id))
+        {
+            self.logger
+                .warn("Commands of added or removed guilds are only synced after a restart");
+        }
+
+        *self
+            .settings
+            .write()
+            .unwrap_or_else(PoisonError::into_inner) = Arc::new(file.into_settings());
+
+        Ok(())
+    }
+
+    fn new() -> Self {
+        let (path, required) = config_path();
+
+        match Self::load(&path, required) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Failed to load config: {}", e);
+                std::process::exit(1);
+            }
         }
     }
 }
@@ -0,0 +1,69 @@
+use crate::config::{Config, ConfigError};
+use poise::serenity_prelude::{CreateEmbed, CreateMessage, Http};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+/// How often the config file is checked for changes.
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Reloads the config and posts the result to the config reload channel of every guild.
+///
+/// `reason` is shown in the posted embed, e.g. who triggered the reload.
+pub async fn reload_config(http: &Http, config: &Config, reason: &str) -> Result<(), ConfigError> {
+    let result = config.reload();
+
+    let embed = match &result {
+        Ok(()) => {
+            config.logger.info(&format!("Reloaded config ({})", reason));
+            CreateEmbed::new()
+                .title("Config Reloaded!")
+                .description(reason)
+                .color(0x00FF88)
+        }
+        Err(e) => {
+            config
+                .logger
+                .error(&format!("Failed to reload config ({}): {}", reason, e));
+            CreateEmbed::new()
+                .title("Config Reload Failed!")
+                .description(format!("{}\n```\n{}\n```", reason, e))
+                .color(0xFF0000)
+        }
+    };
+
+    for guild in config.settings().guilds.values() {
+        guild
+            .config_reload_channel
+            .send_message(http, CreateMessage::new().embed(embed.clone()))
+            .await
+            .ok();
+    }
+
+    result
+}
+
+/// Polls the config file and reloads it whenever its modification time changes. Never returns.
+pub async fn watch_config(http: Arc<Http>, config: &Config) {
+    let modified = |config: &Config| -> Option<SystemTime> {
+        std::fs::metadata(&config.path)
+            .and_then(|m| m.modified())
+            .ok()
+    };
+
+    let mut last_modified = modified(config);
+    let mut interval = tokio::time::interval(WATCH_INTERVAL);
+
+    loop {
+        interval.tick().await;
+
+        let current = modified(config);
+        if current.is_none() || current == last_modified {
+            continue;
+        }
+        last_modified = current;
+
+        reload_config(&http, config, "Config file changed on disk")
+            .await
+            .ok();
+    }
+}
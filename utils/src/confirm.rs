@@ -0,0 +1,88 @@
+//! Confirmation prompts for destructive commands.
+
+use crate::shared_types::{Context, Error};
+use poise::serenity_prelude::{
+    ButtonStyle, ComponentInteractionCollector, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage,
+};
+use poise::{CreateReply, ReplyHandle};
+use std::time::Duration;
+
+/// How long the buttons of a prompt wait for the invoking user.
+pub const CONFIRM_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// The answer to a prompt sent by [`confirm`].
+pub enum Confirmation<'a> {
+    /// The user pressed Confirm. The prompt is still shown without buttons and can be edited
+    /// through the handle, e.g. to show the progress.
+    Confirmed(ReplyHandle<'a>),
+    /// The user pressed Cancel
+    Cancelled,
+    /// The user did not answer within [`CONFIRM_TIMEOUT`]
+    TimedOut,
+}
+
+/// Sends an ephemeral prompt with Confirm and Cancel buttons and waits up to [`CONFIRM_TIMEOUT`]
+/// for the invoking user to press one of them.
+///
+/// Cancelled and timed out prompts are updated to say so, the command only has to return.
+pub async fn confirm<'a>(
+    ctx: Context<'a>,
+    prompt: impl Into<String>,
+) -> Result<Confirmation<'a>, Error> {
+    let prompt = prompt.into();
+    let ctx_id = ctx.id();
+    let confirm_id = format!("{}confirm", ctx_id);
+    let cancel_id = format!("{}cancel", ctx_id);
+    let buttons = CreateActionRow::Buttons(vec![
+        CreateButton::new(&confirm_id)
+            .label("Confirm")
+            .style(ButtonStyle::Danger),
+        CreateButton::new(&cancel_id)
+            .label("Cancel")
+            .style(ButtonStyle::Secondary),
+    ]);
+    let reply = CreateReply::default()
+        .content(&prompt)
+        .components(vec![buttons])
+        .ephemeral(true);
+    let handle = ctx.send(reply).await?;
+
+    let ids = [confirm_id.clone(), cancel_id];
+    let press = ComponentInteractionCollector::new(ctx)
+        .author_id(ctx.author().id)
+        .filter(move |press| ids.contains(&press.data.custom_id))
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+
+    let Some(press) = press else {
+        let reply = CreateReply::default()
+            .content(format!("{}\n\n:x: Timed out, nothing was done.", prompt))
+            .components(Vec::new());
+        handle.edit(ctx, reply).await?;
+        return Ok(Confirmation::TimedOut);
+    };
+
+    let confirmed = press.data.custom_id == confirm_id;
+    let content = if confirmed {
+        prompt
+    } else {
+        format!("{}\n\n:x: Cancelled, nothing was done.", prompt)
+    };
+    press
+        .create_response(
+            ctx.serenity_context(),
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .components(Vec::new()),
+            ),
+        )
+        .await?;
+
+    if confirmed {
+        Ok(Confirmation::Confirmed(handle))
+    } else {
+        Ok(Confirmation::Cancelled)
+    }
+}
@@ -0,0 +1,334 @@
+//! Cooldowns of commands, configured in [`CommandRules::cooldown`](crate::config::CommandRules).
+//!
+//! The state does not depend on Discord, the time is passed in by the caller.
+
+use crate::config::CooldownConfig;
+use poise::serenity_prelude::{ChannelId, GuildId, RoleId, UserId};
+use std::collections::HashMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// What a cooldown applies to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum CooldownBucket {
+    /// A single user
+    User(UserId),
+    /// Everyone in a channel
+    Channel(ChannelId),
+    /// Everyone in a guild
+    Guild(GuildId),
+    /// Everyone everywhere
+    Global,
+}
+
+/// The invocation a cooldown is checked for.
+#[derive(Debug, Clone, Copy)]
+pub struct Invocation<'a> {
+    /// The invoking user
+    pub user_id: UserId,
+    /// The channel the command was used in
+    pub channel_id: ChannelId,
+    /// The guild the command was used in, if any
+    pub guild_id: Option<GuildId>,
+    /// The roles of the invoking member. Only needed if the cooldowns have bypass roles.
+    pub roles: &'a [RoleId],
+}
+
+/// When the cooldowns of the commands end. Part of the shared [`Data`](crate::shared_types::Data).
+#[derive(Debug, Default)]
+pub struct Cooldowns {
+    ends: Mutex<HashMap<(String, CooldownBucket), Instant>>,
+}
+
+impl Cooldowns {
+    /// Creates an empty state, no command is on cooldown.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the remaining time of the longest running cooldown of the command that applies to
+    /// the invocation. If there is none, the use is recorded, starting every configured cooldown,
+    /// and `None` is returned. Members with a bypass role are neither limited nor recorded.
+    pub fn check(
+        &self,
+        command: &str,
+        config: &CooldownConfig,
+        invocation: &Invocation<'_>,
+        now: Instant,
+    ) -> Option<Duration> {
+        if config
+            .bypass_roles
+            .iter()
+            .any(|role| invocation.roles.contains(role))
+        {
+            return None;
+        }
+
+        let mut buckets = Vec::new();
+        let mut add = |bucket: CooldownBucket, secs: u64| {
+            if secs > 0 {
+                buckets.push((bucket, Duration::from_secs(secs)));
+            }
+        };
+        add(CooldownBucket::User(invocation.user_id), config.user_secs);
+        add(
+            CooldownBucket::Channel(invocation.channel_id),
+            config.channel_secs,
+        );
+        if let Some(guild_id) = invocation.guild_id {
+            add(CooldownBucket::Guild(guild_id), config.guild_secs);
+        }
+        add(CooldownBucket::Global, config.global_secs);
+        if buckets.is_empty() {
+            return None;
+        }
+
+        let mut ends = self.ends.lock().unwrap_or_else(PoisonError::into_inner);
+        let remaining = buckets
+            .iter()
+            .filter_map(|(bucket, _)| ends.get(&(command.to_string(), *bucket)))
+            .filter_map(|end| end.checked_duration_since(now))
+            .filter(|remaining| !remaining.is_zero())
+            .max();
+        if remaining.is_some() {
+            return remaining;
+        }
+
+        // Ended cooldowns are not needed anymore
+        ends.retain(|_, end| *end > now);
+        for (bucket, duration) in buckets {
+            ends.insert((command.to_string(), bucket), now + duration);
+        }
+        None
+    }
+}
+
+/// Formats the remaining time of a cooldown like `42s` or `2m 5s`, rounded up to seconds.
+pub fn format_remaining(remaining: Duration) -> String {
+    let mut secs = remaining.as_secs();
+    if remaining.subsec_nanos() > 0 {
+        secs += 1;
+    }
+    match (secs / 60, secs % 60) {
+        (0, secs) => format!("{}s", secs),
+        (minutes, 0) => format!("{}m", minutes),
+        (minutes, secs) => format!("{}m {}s", minutes, secs),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COMMAND: &str = "say";
+
+    fn invocation(user: u64, channel: u64, guild: u64) -> Invocation<'static> {
+        Invocation {
+            user_id: UserId::new(user),
+            channel_id: ChannelId::new(channel),
+            guild_id: Some(GuildId::new(guild)),
+            roles: &[],
+        }
+    }
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn user_cooldown_only_limits_the_same_user() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            user_secs: 10,
+            ..Default::default()
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+            None
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 2, 2), now + secs(4)),
+            Some(secs(6))
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 1, 1), now + secs(4)),
+            None
+        );
+    }
+
+    #[test]
+    fn channel_cooldown_limits_everyone_in_the_channel() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            channel_secs: 10,
+            ..Default::default()
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+            None
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 1, 1), now + secs(1)),
+            Some(secs(9))
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 2, 1), now + secs(1)),
+            None
+        );
+    }
+
+    #[test]
+    fn guild_cooldown_limits_everyone_in_the_guild() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            guild_secs: 10,
+            ..Default::default()
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+            None
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 2, 1), now + secs(2)),
+            Some(secs(8))
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 2, 2), now + secs(2)),
+            None
+        );
+    }
+
+    #[test]
+    fn global_cooldown_limits_everyone() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            global_secs: 10,
+            ..Default::default()
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+            None
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 2, 2), now + secs(3)),
+            Some(secs(7))
+        );
+        assert_eq!(
+            cooldowns.check("embed", &config, &invocation(2, 2, 2), now + secs(3)),
+            None
+        );
+    }
+
+    #[test]
+    fn longest_remaining_cooldown_is_returned() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            user_secs: 30,
+            channel_secs: 5,
+            ..Default::default()
+        };
+        let now = Instant::now();
+
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+            None
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now + secs(1)),
+            Some(secs(29))
+        );
+    }
+
+    #[test]
+    fn cooldown_ends_exactly_after_its_duration() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig {
+            user_secs: 10,
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let user = invocation(1, 1, 1);
+
+        assert_eq!(cooldowns.check(COMMAND, &config, &user, now), None);
+        assert_eq!(
+            cooldowns.check(
+                COMMAND,
+                &config,
+                &user,
+                now + secs(10) - Duration::from_nanos(1)
+            ),
+            Some(Duration::from_nanos(1))
+        );
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &user, now + secs(10)),
+            None
+        );
+        // The use at the end started a new cooldown
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &user, now + secs(15)),
+            Some(secs(5))
+        );
+    }
+
+    #[test]
+    fn bypass_roles_are_not_limited_or_recorded() {
+        let cooldowns = Cooldowns::new();
+        let role = RoleId::new(42);
+        let config = CooldownConfig {
+            channel_secs: 10,
+            bypass_roles: vec![role],
+            ..Default::default()
+        };
+        let now = Instant::now();
+        let roles = [RoleId::new(7), role];
+        let bypassing = Invocation {
+            roles: &roles,
+            ..invocation(1, 1, 1)
+        };
+
+        assert_eq!(cooldowns.check(COMMAND, &config, &bypassing, now), None);
+        assert_eq!(cooldowns.check(COMMAND, &config, &bypassing, now), None);
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 1, 1), now),
+            None
+        );
+        assert_eq!(cooldowns.check(COMMAND, &config, &bypassing, now), None);
+        assert_eq!(
+            cooldowns.check(COMMAND, &config, &invocation(2, 1, 1), now + secs(1)),
+            Some(secs(9))
+        );
+    }
+
+    #[test]
+    fn zero_durations_do_not_limit() {
+        let cooldowns = Cooldowns::new();
+        let config = CooldownConfig::default();
+        let now = Instant::now();
+
+        assert!(!config.is_enabled());
+        for _ in 0..3 {
+            assert_eq!(
+                cooldowns.check(COMMAND, &config, &invocation(1, 1, 1), now),
+                None
+            );
+        }
+    }
+
+    #[test]
+    fn format_remaining_rounds_up_to_seconds() {
+        assert_eq!(format_remaining(Duration::from_millis(1)), "1s");
+        assert_eq!(format_remaining(secs(1)), "1s");
+        assert_eq!(format_remaining(Duration::from_millis(41_200)), "42s");
+        assert_eq!(format_remaining(Duration::from_millis(59_500)), "1m");
+        assert_eq!(format_remaining(secs(60)), "1m");
+        assert_eq!(format_remaining(Duration::from_millis(124_001)), "2m 5s");
+    }
+}
@@ -0,0 +1,268 @@
+use crate::EMBED_DESCRIPTION_LIMIT;
+use crate::config::Config;
+use crate::logging::Field;
+use crate::shared_types::{Context, Data, Error};
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, CreateMessage, GuildId, Timestamp,
+};
+use poise::{CreateReply, FrameworkError};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+static ERROR_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+/// Handles every error of the framework.
+///
+/// Each error is logged with the command, user and guild it happened in and the user gets an
+/// ephemeral reply explaining what went wrong. Unexpected errors get a correlation ID, which is
+/// shown to the user and posted with the error details to the error log channel.
+pub(crate) async fn on_error(error: FrameworkError<'_, Data, Error>, config: &Config) {
+    let logger = &config.logger;
+    let invocation = error.ctx().map(|ctx| ctx.id());
+
+    match error {
+        FrameworkError::Command { error, ctx, .. } => {
+            report_command_error(config, ctx, "Command error", &error.to_string()).await;
+        }
+        FrameworkError::CommandPanic { payload, ctx, .. } => {
+            let payload = payload.unwrap_or_else(|| String::from("no panic payload"));
+            report_command_error(config, ctx, "Command panicked", &payload).await;
+        }
+        FrameworkError::CommandCheckFailed {
+            error: Some(error),
+            ctx,
+            ..
+        } => {
+            report_command_error(config, ctx, "Command check error", &error.to_string()).await;
+        }
+        FrameworkError::CommandCheckFailed {
+            error: None, ctx, ..
+        } => {
+            // The checks reply to the user themselves
+            logger.info(&format!("Check failed for {}", describe(ctx)));
+        }
+        FrameworkError::CommandStructureMismatch {
+            description, ctx, ..
+        } => {
+            let ctx = Context::Application(ctx);
+            report_command_error(
+                config,
+                ctx,
+                "Command structure mismatch, are the commands synced?",
+                description,
+            )
+            .await;
+        }
+        FrameworkError::ArgumentParse {
+            error, input, ctx, ..
+        } => {
+            logger.warn(&format!(
+                "Invalid argument {:?} for {}: {}",
+                input,
+                describe(ctx),
+                error
+            ));
+            let content = match input {
+                Some(input) => format!(":x: Invalid argument `{}`: {}", input, error),
+                None => format!(":x: Invalid arguments: {}", error),
+            };
+            reply(config, ctx, content).await;
+        }
+        FrameworkError::SubcommandRequired { ctx } => {
+            logger.info(&format!("Missing subcommand for {}", describe(ctx)));
+            reply(config, ctx, ":x: Please choose a subcommand!").await;
+        }
+        FrameworkError::CooldownHit {
+            remaining_cooldown,
+            ctx,
+            ..
+        } => {
+            logger.info(&format!("Cooldown hit for {}", describe(ctx)));
+            let content = format!(
+                ":hourglass: Please wait {:.1}s before using this command again!",
+                remaining_cooldown.as_secs_f64()
+            );
+            reply(config, ctx, content).await;
+        }
+        FrameworkError::MissingBotPermissions {
+            missing_permissions,
+            ctx,
+            ..
+        } => {
+            logger.warn(&format!(
+                "Bot is missing permissions {} for {}",
+                missing_permissions,
+                describe(ctx)
+            ));
+            let content = format!(
+                ":x: I am missing these permissions to run this command: {}",
+                missing_permissions
+            );
+            reply(config, ctx, content).await;
+        }
+        FrameworkError::MissingUserPermissions {
+            missing_permissions,
+            ctx,
+            ..
+        } => {
+            logger.info(&format!(
+                "User is missing permissions {:?} for {}",
+                missing_permissions,
+                describe(ctx)
+            ));
+            let content = match missing_permissions {
+                Some(permissions) => format!(
+                    ":x: You need these permissions to use this command: {}",
+                    permissions
+                ),
+                None => String::from(":x: You do not have permission to use this command!"),
+            };
+            reply(config, ctx, content).await;
+        }
+        FrameworkError::NotAnOwner { ctx, .. } => {
+            logger.warn(&format!("Non-owner tried to use {}", describe(ctx)));
+            reply(config, ctx, ":x: Only bot owners can use this command!").await;
+        }
+        FrameworkError::GuildOnly { ctx, .. } => {
+            logger.info(&format!("Guild only {}", describe(ctx)));
+            reply(config, ctx, ":x: This command can only be used in servers!").await;
+        }
+        FrameworkError::DmOnly { ctx, .. } => {
+            logger.info(&format!("DM only {}", describe(ctx)));
+            reply(config, ctx, ":x: This command can only be used in DMs!").await;
+        }
+        FrameworkError::NsfwOnly { ctx, .. } => {
+            logger.info(&format!("NSFW only {}", describe(ctx)));
+            reply(
+                config,
+                ctx,
+                ":x: This command can only be used in NSFW channels!",
+            )
+            .await;
+        }
+        FrameworkError::Setup { error, ctx, .. } => {
+            report_error(config, ctx, None, "Setup error", &error.to_string()).await;
+        }
+        FrameworkError::EventHandler {
+            error, ctx, event, ..
+        } => {
+            let title = format!("Error in {} event handler", event.snake_case_name());
+            report_error(config, ctx, None, &title, &error.to_string()).await;
+        }
+        FrameworkError::UnknownInteraction { interaction, .. } => {
+            logger.warn(&format!(
+                "Received unknown interaction '{}' from {} ({})",
+                interaction.data.name, interaction.user.name, interaction.user.id
+            ));
+        }
+        error => {
+            logger.warn(&format!("Unhandled framework error: {}", error));
+        }
+    }
+
+    if let Some(id) = invocation {
+        crate::shutdown::command_finished(id);
+    }
+}
+
+/// Describes the command invocation for log messages.
+fn describe(ctx: Context<'_>) -> String {
+    format!(
+        "command '{}' by {} ({}) in {}",
+        ctx.command().qualified_name,
+        ctx.author().name,
+        ctx.author().id,
+        match ctx.guild_id() {
+            Some(guild_id) => format!("guild {}", guild_id),
+            None => String::from("DMs"),
+        }
+    )
+}
+
+async fn reply(config: &Config, ctx: Context<'_>, content: impl Into<String>) {
+    let reply = CreateReply::default().content(content).ephemeral(true);
+    if let Err(e) = ctx.send(reply).await {
+        config
+            .logger
+            .error(&format!("Failed to send error reply: {}", e));
+    }
+}
+
+async fn report_command_error(config: &Config, ctx: Context<'_>, title: &str, details: &str) {
+    let title = format!("{} in {}", title, describe(ctx));
+    let id = report_error(
+        config,
+        ctx.serenity_context(),
+        ctx.guild_id(),
+        &title,
+        details,
+    )
+    .await;
+    let content = format!(
+        ":x: Something went wrong while running this command. Reference: `{}`",
+        id
+    );
+    reply(config, ctx, content).await;
+}
+
+/// Logs an unexpected error and posts it to the error log channel of the guild, or of every
+/// guild if `guild_id` is None. Returns the correlation ID of the error.
+async fn report_error(
+    config: &Config,
+    ctx: &serenity::Context,
+    guild_id: Option<GuildId>,
+    title: &str,
+    details: &str,
+) -> String {
+    let id = format!(
+        "{:X}-{}",
+        chrono::Utc::now().timestamp(),
+        ERROR_COUNTER.fetch_add(1, Ordering::Relaxed)
+    );
+
+    {
+        let guild = guild_id.map(|guild_id| guild_id.to_string());
+        let mut fields: Vec<Field<'_>> = vec![("error_id", &id)];
+        if let Some(guild) = &guild {
+            fields.push(("guild_id", guild));
+        }
+        config
+            .logger
+            .error_with(&format!("[{}] {}: {}", id, title, details), &fields);
+    }
+
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(format!(
+            "```\n{}\n```",
+            details
+                .chars()
+                .take(EMBED_DESCRIPTION_LIMIT - "```\n\n```".len())
+                .collect::<String>()
+        ))
+        .field("Reference", format!("`{}`", id), false)
+        .color(0xFF0000)
+        .timestamp(Timestamp::now());
+
+    let settings = config.settings();
+    let channels: Vec<ChannelId> = match guild_id {
+        Some(guild_id) => settings
+            .guild(guild_id)
+            .map(|guild| guild.error_log_channel)
+            .into_iter()
+            .collect(),
+        None => settings
+            .guilds
+            .values()
+            .map(|guild| guild.error_log_channel)
+            .collect(),
+    };
+
+    for channel in channels {
+        channel
+            .send_message(&ctx.http, CreateMessage::new().embed(embed.clone()))
+            .await
+            .ok();
+    }
+
+    id
+}
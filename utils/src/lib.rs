@@ -4,17 +4,48 @@
 pub mod logging;
 pub mod shared_types;
 pub mod config;
+pub mod storage;
 
 mod command_check;
 pub use command_check::check;
 
+pub mod command_sync;
+pub mod confirm;
+pub mod cooldown;
+mod error_handler;
+pub mod maintenance;
+pub mod shutdown;
+
+mod config_reload;
+pub use config_reload::{reload_config, watch_config};
+
+use crate::config::Config;
+use crate::cooldown::Cooldowns;
+use crate::logging::DiscordSink;
+use crate::maintenance::Maintenance;
 use crate::shared_types::{Data, Error};
+use crate::storage::Storage;
+use once_cell::sync::OnceCell;
 use poise::Framework;
 use poise::serenity_prelude::{
-    ActivityData, ChannelId, Client, ClientBuilder, Command, Context, CreateEmbed, CreateMessage,
-    Error as SerenityError, EventHandler, GatewayIntents, GuildId, OnlineStatus, Ready,
+    ActivityData, Client, ClientBuilder, Context, CreateEmbed, CreateMessage,
+    Error as SerenityError, EventHandler, GatewayIntents, OnlineStatus, Ready,
 };
 
+/// The maximum number of characters Discord accepts in an embed description.
+pub const EMBED_DESCRIPTION_LIMIT: usize = 4096;
+
+/// The config passed to [`get_framework`]. The framework callbacks are plain functions and
+/// cannot capture it.
+static FRAMEWORK_CONFIG: OnceCell<&'static Config> = OnceCell::new();
+
+/// Returns the config of the framework for its callbacks, like the error handler and the global
+/// command check.
+fn framework_config() -> &'static Config {
+    // Safe to unwrap since the callbacks only run in the framework built by `get_framework`
+    FRAMEWORK_CONFIG.get().unwrap()
+}
+
 /// Creates and returns a Serenity Client with the given framework, event handler, token, and
 /// intents.
 pub async fn get_client(
@@ -32,8 +63,7 @@ pub async fn get_client(
 fn setup<'a>(
     ctx: &'a Context,
     framework: &'a Framework<Data, Error>,
-    guild_id: GuildId,
-    commands_synced_channel: ChannelId,
+    config: &'static Config,
 ) -> poise::BoxFuture<'a, Result<Data, Error>> {
     Box::pin(async move {
         ctx.set_presence(
@@ -41,67 +71,121 @@ fn setup<'a>(
             OnlineStatus::Idle,
         );
 
-        let sync_start = std::time::Instant::now();
-
-        let old_guild_commands = guild_id.get_commands_with_localizations(&ctx.http).await?;
-
-        for command in old_guild_commands {
-            guild_id.delete_command(&ctx.http, command.id).await?;
+        if let Some(channel_id) = config.logging.discord_channel {
+            config.logger.add_sink(DiscordSink::new(
+                ctx.http.clone(),
+                channel_id,
+                config.logging.discord_min_level.clone(),
+            ));
         }
 
-        let old_global_commands = Command::get_global_commands(&ctx.http).await?;
-
-        for command in old_global_commands {
-            Command::delete_global_command(&ctx.http, command.id).await?;
+        let storage = Storage::open(&config.database_path)?;
+        let maintenance = Maintenance::load(&storage)?;
+        if maintenance.is_enabled() {
+            config
+                .logger
+                .warn("Maintenance mode is on, only owners can use commands");
         }
 
-        poise::builtins::register_in_guild(&ctx.http, &framework.options().commands, guild_id)
-            .await?;
+        let settings = config.settings();
 
-        let sync_duration = sync_start.elapsed();
+        match command_sync::delete_global_commands(&ctx.http, false).await {
+            Ok(deleted) if !deleted.is_empty() => config
+                .logger
+                .info(&format!("Deleted global commands: {}", deleted.join(", "))),
+            Ok(_) => {}
+            Err(e) => config
+                .logger
+                .error(&format!("Failed to delete global commands: {}", e)),
+        }
 
-        let commands_synced_embed = CreateEmbed::new()
-            .title("Commands Synced!")
-            .description(format!(
-                " Took {:.2?}s to sync commands.",
-                sync_duration.as_secs_f64()
-            ))
-            .color(0x00FF88);
-        let commands_synced_message = CreateMessage::new().embed(commands_synced_embed);
+        for (guild_id, guild) in &settings.guilds {
+            let sync_start = std::time::Instant::now();
 
-        commands_synced_channel
-            .send_message(&ctx.http, commands_synced_message)
+            let summary = match command_sync::sync_commands(
+                &ctx.http,
+                *guild_id,
+                &framework.options().commands,
+                guild,
+                false,
+            )
             .await
-            .ok();
+            {
+                Ok(summary) => summary,
+                Err(e) => {
+                    config.logger.error(&format!(
+                        "Failed to sync commands in guild {}: {}",
+                        guild_id, e
+                    ));
+                    continue;
+                }
+            };
+
+            let sync_duration = sync_start.elapsed();
+
+            let commands_synced_embed = CreateEmbed::new()
+                .title("Commands Synced!")
+                .description(format!(
+                    "Took {:.2?}s to sync commands.\n\n{}",
+                    sync_duration.as_secs_f64(),
+                    summary.describe()
+                ))
+                .color(0x00FF88);
+            let commands_synced_message = CreateMessage::new().embed(commands_synced_embed);
+
+            guild
+                .commands_synced_channel
+                .send_message(&ctx.http, commands_synced_message)
+                .await
+                .ok();
+        }
 
-        Ok(Data {})
+        Ok(Data {
+            storage,
+            maintenance,
+            cooldowns: Cooldowns::new(),
+        })
     })
 }
 
 fn setup_wrapper(
-    guild_id: GuildId,
-    commands_synced_channel: ChannelId,
+    config: &'static Config,
 ) -> impl for<'a> Fn(
     &'a Context,
     &'a Ready,
     &'a Framework<Data, Error>,
 ) -> poise::BoxFuture<'a, Result<Data, Error>> {
     move |ctx: &Context, _ready: &Ready, framework: &Framework<Data, Error>| {
-        setup(ctx, framework, guild_id, commands_synced_channel)
+        setup(ctx, framework, config)
     }
 }
 
-/// Creates and returns a Poise Framework with the given commands, guild ID, and commands synced
+/// Creates and returns a Poise Framework with the given commands, which are synced to every
+/// guild configured in `config` on startup. Running commands are tracked for
+/// [`shutdown::shutdown`], which rejects new ones. Commands missing from the `enabled_commands`
+/// of a guild are rejected there. While in maintenance mode, only owners can use commands.
 pub async fn get_framework(
     commands: Vec<poise::Command<Data, Error>>,
-    guild_id: GuildId,
-    commands_synced_channel: ChannelId,
+    config: &'static Config,
 ) -> poise::Framework<Data, Error> {
+    FRAMEWORK_CONFIG.set(config).ok();
+
     poise::Framework::<Data, Error>::builder()
         .options(poise::FrameworkOptions {
             commands,
+            on_error: |error| Box::pin(error_handler::on_error(error, framework_config())),
+            command_check: Some(|ctx| {
+                Box::pin(async move {
+                    let config = framework_config();
+                    Ok(shutdown::check(ctx).await?
+                        && command_check::check_enabled(ctx, config).await?
+                        && maintenance::check(ctx, config).await?)
+                })
+            }),
+            pre_command: |ctx| Box::pin(async move { shutdown::command_started(ctx.id()) }),
+            post_command: |ctx| Box::pin(async move { shutdown::command_finished(ctx.id()) }),
             ..Default::default()
         })
-        .setup(setup_wrapper(guild_id, commands_synced_channel))
+        .setup(setup_wrapper(config))
         .build()
 }
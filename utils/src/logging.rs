@@ -1,13 +1,44 @@
 //! A simple logging utility module.
 
+pub mod bridge;
+mod discord;
+mod filter;
+mod rotation;
+mod search;
+mod sink;
+mod template;
+mod writer;
+
+pub use discord::DiscordSink;
+pub use filter::{ParseFilterError, TargetFilter};
+pub use rotation::RotationPolicy;
+pub use search::{LogEntry, LogQuery, parse_time};
+pub use sink::{FileSink, Record, Sink, StdoutSink};
+pub(crate) use template::Template;
+pub use template::TemplateError;
+pub use writer::OverflowPolicy;
+
 use lazy_static::lazy_static;
 use regex::Regex;
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs::OpenOptions;
-use std::io::Write;
+use std::fmt;
+use std::io::{self, IsTerminal};
+use std::panic::Location;
+use std::path::Path;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::{Duration, Instant};
+use template::{Placeholder, Token};
+use writer::Writer;
 
 static DEFAULT_TIMEZONE: chrono_tz::Tz = chrono_tz::Europe::Berlin;
 
+/// The default number of messages the background writer buffers.
+pub const DEFAULT_BUFFER_CAPACITY: usize = 1024;
+
+/// How long [`Logger::flush`] waits for the background writer.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
 lazy_static! {
     static ref COLOR_REGEX: Regex = Regex::new(r"^#[0-9a-fA-F]{6}$").unwrap();
 }
@@ -41,6 +72,93 @@ impl Color {
     pub fn ansi_code(&self) -> String {
         format!("\x1b[38;2;{};{};{}m", self.0, self.1, self.2)
     }
+
+    /// Like [`Color::ansi_code`], but approximates the color if the terminal does not support
+    /// 24-bit colors. Returns an empty string if it supports no colors at all.
+    pub fn ansi_code_for(&self, support: ColorSupport) -> String {
+        match support {
+            ColorSupport::None => String::new(),
+            ColorSupport::Ansi16 => format!("\x1b[{}m", self.ansi_16()),
+            ColorSupport::Ansi256 => format!("\x1b[38;5;{}m", self.ansi_256()),
+            ColorSupport::TrueColor => self.ansi_code(),
+        }
+    }
+
+    /// Returns the closest color of the 256 color palette.
+    fn ansi_256(&self) -> u8 {
+        let Self(r, g, b) = *self;
+        if r == g && g == b {
+            // Grayscale ramp from 232 (almost black) to 255 (almost white)
+            return match r {
+                0..8 => 16,
+                249.. => 231,
+                _ => 232 + ((r as u16 - 8) * 24 / 247) as u8,
+            };
+        }
+
+        // 6x6x6 color cube starting at 16
+        let scale = |c: u8| (c as u16 * 5 + 127) / 255;
+        (16 + 36 * scale(r) + 6 * scale(g) + scale(b)) as u8
+    }
+
+    /// Returns the foreground code of the closest of the 16 basic colors.
+    fn ansi_16(&self) -> u8 {
+        let Self(r, g, b) = *self;
+        let max = r.max(g).max(b);
+        let index = (b > 127) as u8 * 4 + (g > 127) as u8 * 2 + (r > 127) as u8;
+        match index {
+            // Gray is shown as bright black
+            0 if max >= 64 => 90,
+            0 => 30,
+            _ if max > 191 => 90 + index,
+            _ => 30 + index,
+        }
+    }
+}
+
+/// How many colors the terminal supports.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Deserialize)]
+pub enum ColorSupport {
+    /// No colors or other ANSI codes
+    #[serde(rename = "none")]
+    None,
+    /// The 16 basic colors
+    #[serde(rename = "16")]
+    Ansi16,
+    /// The 256 color palette
+    #[serde(rename = "256")]
+    Ansi256,
+    /// 24-bit colors
+    #[serde(rename = "truecolor")]
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Detects the color support of stdout.
+    ///
+    /// There are no colors if the `NO_COLOR` environment variable is set and not empty, if stdout
+    /// is not a terminal, e.g. when running under systemd or docker, or if `TERM` is `dumb`.
+    /// Otherwise `COLORTERM=truecolor` or `COLORTERM=24bit` enables 24-bit colors, a `TERM` like
+    /// `xterm-256color` enables 256 colors, and everything else gets the 16 basic colors.
+    pub fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default();
+
+        if !var("NO_COLOR").is_empty() || !std::io::stdout().is_terminal() {
+            return Self::None;
+        }
+
+        let term = var("TERM");
+        let colorterm = var("COLORTERM");
+        if term == "dumb" {
+            Self::None
+        } else if colorterm == "truecolor" || colorterm == "24bit" {
+            Self::TrueColor
+        } else if term.contains("256color") {
+            Self::Ansi256
+        } else {
+            Self::Ansi16
+        }
+    }
 }
 
 impl Default for Color {
@@ -58,15 +176,26 @@ impl Default for Color {
 /// - `{{levelc}}`: Log level in color
 /// - `{{message}}`: Log message
 /// - `{{messagec}}`: Log message in color
+/// - `{{target}}`: Module path the message was logged from, e.g. `commands::purge`
+/// - `{{file}}`: Source file the message was logged from, `?` if unknown
+/// - `{{line}}`: Line the message was logged from, `?` if unknown
+/// - `{{thread}}`: Name of the logging thread, or its id if it has no name
+/// - `{{elapsed}}`: Seconds since the logger was built, e.g. `12.345s`
+/// - `{{fields}}`: Fields attached to the message as `key=value` pairs
 ///
 /// # Valid Formatting
 /// - `*Text*`: Bold
 /// - `_Text_`: Italic
 /// - `~#RRGGBBText~`: Colored text (hex color code)
+/// - `\*`: A literal `*`, works for any character
 ///
 /// # Notes
-/// - ANSI codes will be omitted in the output file.
-/// - Template string will not be validated.
+/// - The template is parsed when the [`Logger`] is built, which fails with a [`TemplateError`]
+///   for unknown placeholders and unclosed formatting.
+/// - ANSI codes will be omitted in the output file, and in the terminal if it does not support
+///   colors, see [`ColorSupport::detect`].
+/// - With [`FileFormat::Json`] the output file gets JSON lines instead of the template.
+/// - Without `{{fields}}`, the fields are appended to the message.
 /// - You cannot nest color formatting.
 #[derive(Debug, Clone, Eq, PartialEq)]
 pub struct LoggingFormat {
@@ -84,8 +213,25 @@ pub struct LoggingFormat {
     /// Colors for different log levels when using `{{messagec}}`. Missing levels default to
     /// [`Default::default()`].
     pub message_colors: HashMap<LogLevel, Color>,
+    /// Format of the lines written to the output file. The terminal always uses the template.
+    pub file_format: FileFormat,
+}
+
+/// Format of the lines written to the output file.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileFormat {
+    /// The template without ANSI codes, followed by the fields as `key=value` pairs
+    #[default]
+    Template,
+    /// One JSON object per line with the keys `timestamp` (RFC 3339), `level` and `message`,
+    /// followed by the fields. Fields named like one of these keys are ignored.
+    Json,
 }
 
+/// A key/value pair attached to a log message, e.g. `("guild_id", &guild_id)`.
+pub type Field<'a> = (&'a str, &'a dyn fmt::Display);
+
 impl Default for LoggingFormat {
     fn default() -> Self {
         let mut default_colors: HashMap<LogLevel, Color> = HashMap::new();
@@ -101,12 +247,14 @@ impl Default for LoggingFormat {
             timezone: DEFAULT_TIMEZONE,
             level_colors: default_colors.clone(),
             message_colors: default_colors.clone(),
+            file_format: FileFormat::default(),
         }
     }
 }
 
 /// Enum for different logging levels.
-#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Ord, PartialOrd)]
+#[derive(Debug, Clone, Default, Eq, PartialEq, Hash, Ord, PartialOrd, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum LogLevel {
     /// Shows debug log messages
     Debug,
@@ -121,25 +269,91 @@ pub enum LogLevel {
     Off,
 }
 
+impl LogLevel {
+    /// Returns the name of the level as used in log messages, e.g. `WARN`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LogLevel::Debug => "DEBUG",
+            LogLevel::Info => "INFO",
+            LogLevel::Warn => "WARN",
+            LogLevel::Error => "ERROR",
+            LogLevel::Off => "OFF",
+        }
+    }
+}
+
 /// A simple logger with configurable log levels, output file, and formatting.
+///
+/// Messages are formatted on the calling thread and passed to a background thread, which writes
+/// them to the sinks: stdout, the output file and any sink added with [`Logger::add_sink`]. Use
+/// [`Logger::flush`] before the process exits. When the buffer is full, the [`OverflowPolicy`]
+/// decides what happens to new messages. The output file is rotated according to the
+/// [`RotationPolicy`].
+///
+/// Messages are filtered by the target they are logged from, which is derived from the source
+/// file, e.g. `commands::purge` for `src/commands/purge.rs`. The levels can be changed at runtime,
+/// see [`Logger::set_target_level`]. Clones share the levels, the background writer and its sinks.
 #[derive(Debug, Clone)]
 pub struct Logger {
-    log_level: LogLevel,
+    levels: Arc<RwLock<Levels>>,
     output_file: Option<String>,
+    file_min_level: LogLevel,
+    rotation: RotationPolicy,
+    writer: Arc<Writer>,
     format: LoggingFormat,
+    template: Arc<Template>,
+    color_support: ColorSupport,
+    started: Instant,
 }
 
 impl Logger {
-    fn new(
-        log_level: Option<LogLevel>,
-        output_file: Option<String>,
-        format: Option<LoggingFormat>,
-    ) -> Self {
-        Self {
-            log_level: log_level.unwrap_or_default(),
-            output_file,
-            format: format.unwrap_or_default(),
+    fn new(builder: LoggerBuilder) -> Result<Self, TemplateError> {
+        let format = builder.format.unwrap_or_default();
+        let template = Template::parse(&format.template)?;
+        let file_min_level = builder.file_min_level.unwrap_or(LogLevel::Debug);
+
+        let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+        if !builder.stdout_disabled {
+            let min_level = builder.stdout_min_level.unwrap_or(LogLevel::Debug);
+            sinks.push(Box::new(StdoutSink::new(min_level)));
+        }
+        let file = builder.output_file.as_ref().map(|path| {
+            Box::new(FileSink::new(
+                path,
+                file_min_level.clone(),
+                format.file_format,
+                builder.rotation.clone(),
+                format.timezone,
+            )) as Box<dyn Sink>
+        });
+
+        let writer = Writer::spawn(
+            sinks,
+            file,
+            builder.buffer_capacity.unwrap_or(DEFAULT_BUFFER_CAPACITY),
+            builder.overflow_policy.unwrap_or_default(),
+        );
+
+        let mut filter = builder.levels.unwrap_or_default();
+        if let Some(level) = builder.log_level {
+            filter.set_default_level(level);
         }
+        let levels = Levels {
+            filter,
+            dependency_level: builder.dependency_level.unwrap_or(LogLevel::Warn),
+        };
+
+        Ok(Self {
+            levels: Arc::new(RwLock::new(levels)),
+            output_file: builder.output_file,
+            file_min_level,
+            rotation: builder.rotation,
+            writer: Arc::new(writer),
+            format,
+            template: Arc::new(template),
+            color_support: builder.color_support.unwrap_or_else(ColorSupport::detect),
+            started: Instant::now(),
+        })
     }
 
     /// Create a new builder for constructing a Logger.
@@ -147,48 +361,242 @@ impl Logger {
         LoggerBuilder::new()
     }
 
-    /// Log a debug message. Only logs if the log level is set to Debug.
+    /// Log a debug message. Only logs if the level of the target is Debug.
+    #[track_caller]
     pub fn debug(&self, message: &str) {
-        if self.log_level <= LogLevel::Debug {
-            self.log(LogLevel::Debug, message);
-        }
+        self.debug_with(message, &[]);
+    }
+
+    /// Like [`Logger::debug`], but attaches key/value fields to the message.
+    #[track_caller]
+    pub fn debug_with(&self, message: &str, fields: &[Field<'_>]) {
+        self.log_from(LogLevel::Debug, Location::caller(), message, fields);
     }
 
-    /// Log an info message. Only logs if the log level is set to Info or lower.
+    /// Log an info message. Only logs if the level of the target is Info or lower.
+    #[track_caller]
     pub fn info(&self, message: &str) {
-        if self.log_level <= LogLevel::Info {
-            self.log(LogLevel::Info, message);
-        }
+        self.info_with(message, &[]);
     }
 
-    /// Log a warning message. Only logs if the log level is set to Warn or lower.
+    /// Like [`Logger::info`], but attaches key/value fields to the message.
+    #[track_caller]
+    pub fn info_with(&self, message: &str, fields: &[Field<'_>]) {
+        self.log_from(LogLevel::Info, Location::caller(), message, fields);
+    }
+
+    /// Log a warning message. Only logs if the level of the target is Warn or lower.
+    #[track_caller]
     pub fn warn(&self, message: &str) {
-        if self.log_level <= LogLevel::Warn {
-            self.log(LogLevel::Warn, message);
-        }
+        self.warn_with(message, &[]);
     }
 
-    /// Log an error message. Only logs if the log level is set to Error or lower.
+    /// Like [`Logger::warn`], but attaches key/value fields to the message.
+    #[track_caller]
+    pub fn warn_with(&self, message: &str, fields: &[Field<'_>]) {
+        self.log_from(LogLevel::Warn, Location::caller(), message, fields);
+    }
+
+    /// Log an error message. Only logs if the level of the target is Error or lower.
+    #[track_caller]
     pub fn error(&self, message: &str) {
-        if self.log_level <= LogLevel::Error {
-            self.log(LogLevel::Error, message);
-        }
+        self.error_with(message, &[]);
+    }
+
+    /// Like [`Logger::error`], but attaches key/value fields to the message.
+    #[track_caller]
+    pub fn error_with(&self, message: &str, fields: &[Field<'_>]) {
+        self.log_from(LogLevel::Error, Location::caller(), message, fields);
+    }
+
+    /// Set the log level for targets without their own level. Affects all clones of the logger.
+    pub fn set_log_level(&self, level: LogLevel) {
+        self.write_levels().filter.set_default_level(level);
+    }
+
+    /// Set the log level of a target and everything below it, e.g. `commands::purge` or
+    /// `serenity::gateway`. Affects all clones of the logger.
+    pub fn set_target_level(&self, target: impl Into<String>, level: LogLevel) {
+        self.write_levels().filter.set(target, level);
+    }
+
+    /// Remove the log level of a target, so it uses the level of its parent again. Returns
+    /// whether the target had its own level.
+    pub fn reset_target_level(&self, target: &str) -> bool {
+        self.write_levels().filter.remove(target)
+    }
+
+    /// Set the log level for targets of dependencies without their own level. Messages of
+    /// dependencies are routed to the logger by the [`bridge`].
+    pub fn set_dependency_level(&self, level: LogLevel) {
+        self.write_levels().dependency_level = level;
+    }
+
+    /// Replace all log levels of targets, including the default level.
+    pub fn set_levels(&self, filter: TargetFilter) {
+        self.write_levels().filter = filter;
+    }
+
+    /// Returns the current log levels of targets.
+    pub fn levels(&self) -> TargetFilter {
+        self.read_levels().filter.clone()
+    }
+
+    /// Returns the current log level for targets of dependencies without their own level.
+    pub fn dependency_level(&self) -> LogLevel {
+        self.read_levels().dependency_level.clone()
+    }
+
+    /// Whether a message of the level from the target would be logged.
+    pub fn enabled(&self, target: &str, level: &LogLevel) -> bool {
+        self.read_levels().filter.enabled(target, level)
     }
 
-    /// Set the log level for the logger.
-    pub fn set_log_level(&mut self, level: LogLevel) {
-        self.log_level = level;
+    /// Like [`Logger::enabled`], but for targets of dependencies.
+    fn dependency_enabled(&self, target: &str, level: &LogLevel) -> bool {
+        let levels = self.read_levels();
+        let min_level = levels
+            .filter
+            .directive_for(target)
+            .unwrap_or(&levels.dependency_level);
+        *level != LogLevel::Off && level >= min_level
+    }
+
+    fn read_levels(&self) -> std::sync::RwLockReadGuard<'_, Levels> {
+        self.levels.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_levels(&self) -> std::sync::RwLockWriteGuard<'_, Levels> {
+        self.levels.write().unwrap_or_else(PoisonError::into_inner)
     }
 
     /// Set the output file for the logger. Use `None` to disable file logging.
+    ///
+    /// Affects all clones of the logger. Messages queued before are still written to the previous
+    /// file.
     pub fn set_output_file(&mut self, file: Option<String>) {
+        let sink = file.as_ref().map(|path| {
+            Box::new(FileSink::new(
+                path,
+                self.file_min_level.clone(),
+                self.format.file_format,
+                self.rotation.clone(),
+                self.format.timezone,
+            )) as Box<dyn Sink>
+        });
+        self.writer.set_file(sink);
         self.output_file = file;
     }
 
-    fn log(&self, level: LogLevel, message: &str) {
-        let template = &self.format.template;
-        let chars: Vec<char> = template.chars().collect();
-        let chars_len = chars.len();
+    /// Add a sink, e.g. a [`DiscordSink`]. Affects all clones of the logger.
+    pub fn add_sink(&self, sink: impl Sink + 'static) {
+        self.writer.add_sink(Box::new(sink));
+    }
+
+    /// Wait until all queued messages are written and flushed. Gives up after a few seconds and
+    /// returns whether everything was flushed.
+    pub fn flush(&self) -> bool {
+        self.writer.flush(FLUSH_TIMEOUT)
+    }
+
+    /// Returns the number of messages dropped so far because the buffer was full.
+    pub fn dropped_messages(&self) -> u64 {
+        self.writer.dropped()
+    }
+
+    /// Returns the output file, if file logging is enabled.
+    pub fn output_file(&self) -> Option<&str> {
+        self.output_file.as_deref()
+    }
+
+    /// Returns the timezone of the timestamps.
+    pub fn timezone(&self) -> chrono_tz::Tz {
+        self.format.timezone
+    }
+
+    /// Returns the last `count` entries of the output file, oldest first.
+    ///
+    /// Flushes the logger and reads the file, so this blocks and should not be called from an
+    /// async task directly.
+    pub fn tail(&self, count: usize) -> io::Result<Vec<LogEntry>> {
+        self.search(&LogQuery::default(), count)
+    }
+
+    /// Returns the newest `limit` entries of the output file matching the query, oldest first.
+    /// Rotated files are read as well if the query includes them, compressed or not.
+    ///
+    /// Entries are recognized by their timestamp, lines without one belong to the previous entry.
+    /// Flushes the logger and reads the files, so this blocks and should not be called from an
+    /// async task directly.
+    pub fn search(&self, query: &LogQuery, limit: usize) -> io::Result<Vec<LogEntry>> {
+        let Some(path) = &self.output_file else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "File logging is disabled",
+            ));
+        };
+
+        self.flush();
+        search::search(Path::new(path), query, limit, self.format.timezone)
+    }
+
+    fn log_from(
+        &self,
+        level: LogLevel,
+        location: &Location<'_>,
+        message: &str,
+        fields: &[Field<'_>],
+    ) {
+        let target = file_target(location.file());
+        if self.enabled(&target, &level) {
+            let location = Some((location.file(), location.line()));
+            self.log(level, &target, location, message, fields);
+        }
+    }
+
+    /// Logs the message without checking the level. `location` is the source file and line the
+    /// message was logged from, if known.
+    fn log(
+        &self,
+        level: LogLevel,
+        target: &str,
+        location: Option<(&str, u32)>,
+        message: &str,
+        fields: &[Field<'_>],
+    ) {
+        if !self
+            .writer
+            .write(self.render(level, target, location, message, fields))
+        {
+            return;
+        }
+
+        let dropped = self.writer.take_unreported();
+        if dropped > 0 {
+            let message = format!(
+                "Dropped {} log messages because the log buffer was full",
+                dropped
+            );
+            if !self
+                .writer
+                .write_report(self.render(LogLevel::Warn, "", None, &message, &[]))
+            {
+                // Try again with the next message
+                self.writer.restore_unreported(dropped);
+            }
+        }
+    }
+
+    /// Renders the message with the template.
+    fn render(
+        &self,
+        level: LogLevel,
+        target: &str,
+        location: Option<(&str, u32)>,
+        message: &str,
+        fields: &[Field<'_>],
+    ) -> Record {
+        let now = chrono::Utc::now().with_timezone(&self.format.timezone);
 
         let mut term_output = String::new();
         let mut file_output = String::new();
@@ -196,156 +604,113 @@ impl Logger {
         let mut italic = false;
         let mut colored = String::new();
 
-        let mut i = 0;
-        while i < chars_len {
-            let c = chars[i];
-            match c {
-                '{' => {
-                    if !(i + 1 < chars_len && chars[i + 1] == '{') {
-                        term_output.push(c);
-                        file_output.push(c);
-                        i += 1;
-                        continue;
-                    }
-
-                    let j = chars[i..].iter().position(|&x| x == '}');
-                    if j.is_none() {
-                        term_output.push(c);
-                        file_output.push(c);
-                        i += 1;
-                        continue;
-                    }
-
-                    let j = j.unwrap() + i;
-                    if !(j + 1 < chars_len && chars[j + 1] == '}') {
-                        term_output.push(c);
-                        file_output.push(c);
-                        i += 1;
-                        continue;
-                    }
-
-                    let placeholder_string = chars[i + 2..j].iter().collect::<String>();
-                    let placeholder = placeholder_string.as_str();
-
-                    match placeholder {
-                        "timestamp" | "timestampc" => {
-                            let timestamp = chrono::Utc::now()
-                                .with_timezone(&self.format.timezone)
-                                .format(&self.format.timestamp_format)
-                                .to_string();
-                            file_output.push_str(timestamp.as_str());
-                            if placeholder == "timestampc" {
-                                let color_code = self.format.timestamp_color.ansi_code();
-                                term_output.push_str(color_code.as_str());
-                                term_output.push_str(timestamp.as_str());
-                                term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
-                            } else {
-                                term_output.push_str(timestamp.as_str());
-                            }
-                            i = j + 2;
-                            continue;
-                        }
-                        "level" | "levelc" => {
-                            let level_str = match level {
-                                LogLevel::Debug => "DEBUG",
-                                LogLevel::Info => "INFO",
-                                LogLevel::Warn => "WARN",
-                                LogLevel::Error => "ERROR",
-                                _ => "",
-                            };
-                            file_output.push_str(level_str);
-                            if placeholder == "levelc" {
-                                let color = self.format.level_colors.get(&level);
-                                let color_code = color.unwrap_or(&Color::default()).ansi_code();
-                                term_output.push_str(color_code.as_str());
-                                term_output.push_str(level_str);
-                                term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
-                            } else {
-                                term_output.push_str(level_str);
-                            }
-                            i = j + 2;
-                            continue;
-                        }
-                        "message" | "messagec" => {
-                            file_output.push_str(message);
-                            if placeholder == "messagec" {
-                                let color = self.format.message_colors.get(&level);
-                                let color_code = color.unwrap_or(&Color::default()).ansi_code();
-                                term_output.push_str(color_code.as_str());
-                                term_output.push_str(message);
-                                term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
-                            } else {
-                                term_output.push_str(message);
-                            }
-                            i = j + 2;
-                            continue;
-                        }
-                        _ => {
-                            term_output.push(c);
-                            file_output.push(c);
-                        }
-                    }
+        for token in self.template.tokens() {
+            match token {
+                Token::Text(text) => {
+                    term_output.push_str(text);
+                    file_output.push_str(text);
                 }
-                '*' => {
+                Token::Bold => {
                     bold = !bold;
-                    term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
+                    term_output.push_str(&ansi_reset(bold, italic, &colored));
                 }
-                '_' => {
+                Token::Italic => {
                     italic = !italic;
-                    term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
+                    term_output.push_str(&ansi_reset(bold, italic, &colored));
                 }
-                '~' => {
-                    if !colored.is_empty() {
-                        // Closing color tag
-                        colored.clear();
-                        term_output.push_str(ansi_reset(bold, italic, &colored).as_str());
-                        i += 1;
-                        continue;
-                    }
-
-                    if i + 8 >= chars_len {
-                        term_output.push(c);
-                        file_output.push(c);
-                        i += 1;
-                        continue;
-                    }
-                    let hex_code: String = chars[i + 1..i + 8].iter().collect();
-
-                    if !COLOR_REGEX.is_match(&hex_code) {
-                        term_output.push(c);
-                        file_output.push(c);
-                        i += 1;
-                        continue;
+                Token::ColorStart(color) => {
+                    colored = color.ansi_code_for(self.color_support);
+                    term_output.push_str(&colored);
+                }
+                Token::ColorEnd => {
+                    colored.clear();
+                    term_output.push_str(&ansi_reset(bold, italic, &colored));
+                }
+                Token::Placeholder(Placeholder::Fields, _) => {
+                    for (i, (key, value)) in fields.iter().enumerate() {
+                        let separator = if i == 0 { "" } else { " " };
+                        term_output.push_str(&format!("{}\x1b[2m{}=", separator, key));
+                        term_output.push_str(&ansi_reset(bold, italic, &colored));
+                        term_output.push_str(&value.to_string());
+                        file_output.push_str(&format!("{}{}={}", separator, key, value));
                     }
-
-                    // Unwrap is safe due to regex check
-                    let color = Color::hex(&hex_code).unwrap();
-
-                    colored = color.ansi_code();
-                    term_output.push_str(colored.as_str());
-                    i += 8;
-                    continue;
                 }
-                _ => {
-                    term_output.push(c);
-                    file_output.push(c);
+                Token::Placeholder(placeholder, is_colored) => {
+                    let value =
+                        match placeholder {
+                            Placeholder::Timestamp => {
+                                now.format(&self.format.timestamp_format).to_string()
+                            }
+                            Placeholder::Level => level.name().to_string(),
+                            Placeholder::Message => message.to_string(),
+                            Placeholder::Target => target.to_string(),
+                            Placeholder::File => location
+                                .map_or_else(|| String::from("?"), |(file, _)| file.to_string()),
+                            Placeholder::Line => location
+                                .map_or_else(|| String::from("?"), |(_, line)| line.to_string()),
+                            Placeholder::Thread => {
+                                let thread = std::thread::current();
+                                match thread.name() {
+                                    Some(name) => name.to_string(),
+                                    None => format!("{:?}", thread.id()),
+                                }
+                            }
+                            Placeholder::Elapsed => {
+                                format!("{:.3}s", self.started.elapsed().as_secs_f64())
+                            }
+                            // Rendered by the arm above
+                            Placeholder::Fields => continue,
+                        };
+
+                    file_output.push_str(&value);
+                    if *is_colored {
+                        let color = match placeholder {
+                            Placeholder::Timestamp => Some(&self.format.timestamp_color),
+                            Placeholder::Level => self.format.level_colors.get(&level),
+                            _ => self.format.message_colors.get(&level),
+                        };
+                        term_output.push_str(
+                            &color
+                                .cloned()
+                                .unwrap_or_default()
+                                .ansi_code_for(self.color_support),
+                        );
+                        term_output.push_str(&value);
+                        term_output.push_str(&ansi_reset(bold, italic, &colored));
+                    } else {
+                        term_output.push_str(&value);
+                    }
                 }
-            };
-            i += 1;
+            }
         }
 
-        term_output.push_str(ansi_reset(false, false, "").as_str());
-        println!("{}", term_output);
-
-        if let Some(file) = &self.output_file {
-            let mut file = OpenOptions::new()
-                .create(true)
-                .append(true)
-                .open(file)
-                .unwrap();
-            writeln!(file, "{}", file_output).unwrap();
+        term_output.push_str(&ansi_reset(false, false, ""));
+        if !self.template.has_fields() {
+            for (key, value) in fields {
+                term_output.push_str(&format!(" \x1b[2m{}=\x1b[0m{}", key, value));
+            }
+            for (key, value) in fields {
+                file_output.push_str(&format!(" {}={}", key, value));
+            }
         }
 
+        return Record {
+            level,
+            target: target.to_string(),
+            timestamp: now,
+            message: message.to_string(),
+            fields: fields
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            terminal: if self.color_support == ColorSupport::None {
+                file_output.clone()
+            } else {
+                term_output
+            },
+            plain: file_output,
+        };
+
         fn ansi_reset(bold: bool, italic: bool, colored: &str) -> String {
             let mut output = String::from("\x1b[0m");
             if bold {
@@ -362,9 +727,33 @@ impl Logger {
     }
 }
 
+/// The log levels of a [`Logger`], shared by all its clones.
+#[derive(Debug)]
+struct Levels {
+    filter: TargetFilter,
+    dependency_level: LogLevel,
+}
+
+/// Derives the target of a message from the source file it was logged in, e.g.
+/// `bot/src/commands/purge.rs` becomes `commands::purge`. The crate root has an empty target.
+fn file_target(file: &str) -> String {
+    let path = file.replace('\\', "/");
+    let path = match path.find("src/") {
+        Some(i) => &path[i + 4..],
+        None => &path,
+    };
+    let path = path.strip_suffix(".rs").unwrap_or(path);
+    let path = path.strip_suffix("/mod").unwrap_or(path);
+    if path == "main" || path == "lib" {
+        return String::new();
+    }
+    path.replace('/', "::")
+}
+
 impl Default for Logger {
     fn default() -> Self {
-        Self::new(None, None, None)
+        // The default template is valid
+        Self::new(LoggerBuilder::default()).unwrap()
     }
 }
 
@@ -372,7 +761,16 @@ impl Default for Logger {
 #[derive(Debug, Clone, Default)]
 pub struct LoggerBuilder {
     log_level: Option<LogLevel>,
+    levels: Option<TargetFilter>,
+    dependency_level: Option<LogLevel>,
     output_file: Option<String>,
+    file_min_level: Option<LogLevel>,
+    stdout_disabled: bool,
+    stdout_min_level: Option<LogLevel>,
+    color_support: Option<ColorSupport>,
+    rotation: RotationPolicy,
+    buffer_capacity: Option<usize>,
+    overflow_policy: Option<OverflowPolicy>,
     format: Option<LoggingFormat>,
 }
 
@@ -382,26 +780,115 @@ impl LoggerBuilder {
         Default::default()
     }
 
-    /// Set the log level for the logger.
+    /// Set the log level for targets without their own level. Overrides the default level of
+    /// [`LoggerBuilder::levels`].
     pub fn log_level(mut self, level: LogLevel) -> Self {
         self.log_level = Some(level);
         self
     }
 
+    /// Set the log levels of targets.
+    pub fn levels(mut self, levels: TargetFilter) -> Self {
+        self.levels = Some(levels);
+        self
+    }
+
+    /// Set the log level for targets of dependencies without their own level. Defaults to Warn.
+    pub fn dependency_level(mut self, level: LogLevel) -> Self {
+        self.dependency_level = Some(level);
+        self
+    }
+
     /// Set the output file for the logger.
     pub fn output_file(mut self, file: String) -> Self {
         self.output_file = Some(file);
         self
     }
 
+    /// Set the minimum level of messages written to the output file. Messages below the log level
+    /// of the logger are never written.
+    pub fn file_min_level(mut self, level: LogLevel) -> Self {
+        self.file_min_level = Some(level);
+        self
+    }
+
+    /// Enable or disable logging to stdout. Enabled by default.
+    pub fn stdout(mut self, enabled: bool) -> Self {
+        self.stdout_disabled = !enabled;
+        self
+    }
+
+    /// Set the minimum level of messages written to stdout.
+    pub fn stdout_min_level(mut self, level: LogLevel) -> Self {
+        self.stdout_min_level = Some(level);
+        self
+    }
+
+    /// Set the color support of the terminal instead of detecting it with
+    /// [`ColorSupport::detect`].
+    pub fn color_support(mut self, support: ColorSupport) -> Self {
+        self.color_support = Some(support);
+        self
+    }
+
     /// Set the logging format for the logger.
     pub fn format(mut self, format: LoggingFormat) -> Self {
         self.format = Some(format);
         self
     }
 
-    /// Build and return the Logger with the specified settings.
-    pub fn build(self) -> Logger {
-        Logger::new(self.log_level, self.output_file, self.format)
+    /// Set the rotation policy for the output file. Replaces all rotation settings made so far.
+    pub fn rotation(mut self, policy: RotationPolicy) -> Self {
+        self.rotation = policy;
+        self
+    }
+
+    /// Rotate the output file before it grows larger than `bytes`.
+    pub fn max_file_size(mut self, bytes: u64) -> Self {
+        self.rotation.max_size = Some(bytes);
+        self
+    }
+
+    /// Rotate the output file when the day changes in the timezone of the logging format.
+    pub fn rotate_daily(mut self, daily: bool) -> Self {
+        self.rotation.daily = daily;
+        self
+    }
+
+    /// Compress rotated files with gzip.
+    pub fn compress_rotated(mut self, compress: bool) -> Self {
+        self.rotation.compress = compress;
+        self
+    }
+
+    /// Delete rotated files older than `max_age`.
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.rotation.max_age = Some(max_age);
+        self
+    }
+
+    /// Delete the oldest rotated files while all log files together are larger than `bytes`.
+    pub fn max_total_size(mut self, bytes: u64) -> Self {
+        self.rotation.max_total_size = Some(bytes);
+        self
+    }
+
+    /// Set how many messages the background writer buffers. Defaults to
+    /// [`DEFAULT_BUFFER_CAPACITY`].
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = Some(capacity);
+        self
+    }
+
+    /// Set what happens to messages when the buffer is full.
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.overflow_policy = Some(policy);
+        self
+    }
+
+    /// Build and return the Logger with the specified settings. Fails if the template of the
+    /// format is invalid.
+    pub fn build(self) -> Result<Logger, TemplateError> {
+        Logger::new(self)
     }
 }
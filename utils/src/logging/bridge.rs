@@ -0,0 +1,175 @@
+//! Routes `tracing` events and `log` records of dependencies into a [`Logger`].
+
+use super::{Field, LogLevel, Logger};
+use std::fmt::{self, Write};
+use tracing::field::{Field as TracingField, Visit};
+use tracing::subscriber::Interest;
+use tracing::{Event, Metadata, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+
+/// Errors that can occur while installing the bridges.
+#[derive(Debug)]
+pub enum InstallError {
+    /// Another `tracing` subscriber is already installed.
+    Tracing(tracing::subscriber::SetGlobalDefaultError),
+    /// Another `log` logger is already installed.
+    Log(log::SetLoggerError),
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Tracing(e) => write!(f, "Could not install tracing subscriber: {}", e),
+            Self::Log(e) => write!(f, "Could not install log logger: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for InstallError {}
+
+/// Installs a [`TracingLayer`] as the global `tracing` subscriber and a [`LogBridge`] as the
+/// global `log` logger, both writing to `logger`.
+pub fn install(logger: &'static Logger) -> Result<(), InstallError> {
+    let subscriber = tracing_subscriber::registry().with(TracingLayer::new(logger));
+    tracing::subscriber::set_global_default(subscriber).map_err(InstallError::Tracing)?;
+
+    log::set_boxed_logger(Box::new(LogBridge::new(logger))).map_err(InstallError::Log)?;
+    // The bridge filters by target itself
+    log::set_max_level(log::LevelFilter::Trace);
+
+    Ok(())
+}
+
+/// A `tracing` layer that writes events to a [`Logger`].
+///
+/// The `message` field becomes the log message, the other fields are attached as fields. Trace
+/// events are logged as debug messages. Events are filtered by the level of their target, falling
+/// back to the dependency level of the logger.
+#[derive(Debug)]
+pub struct TracingLayer {
+    logger: &'static Logger,
+}
+
+impl TracingLayer {
+    /// Creates a layer that writes events to `logger`.
+    pub fn new(logger: &'static Logger) -> Self {
+        Self { logger }
+    }
+}
+
+fn tracing_level(level: &tracing::Level) -> LogLevel {
+    match *level {
+        tracing::Level::TRACE | tracing::Level::DEBUG => LogLevel::Debug,
+        tracing::Level::INFO => LogLevel::Info,
+        tracing::Level::WARN => LogLevel::Warn,
+        tracing::Level::ERROR => LogLevel::Error,
+    }
+}
+
+impl<S: Subscriber> Layer<S> for TracingLayer {
+    fn register_callsite(&self, _metadata: &'static Metadata<'static>) -> Interest {
+        // The levels can change at runtime, so they have to be checked for every event
+        Interest::sometimes()
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>, _ctx: Context<'_, S>) -> bool {
+        self.logger
+            .dependency_enabled(metadata.target(), &tracing_level(metadata.level()))
+    }
+
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        let mut visitor = EventVisitor::default();
+        event.record(&mut visitor);
+
+        let fields: Vec<Field<'_>> = visitor
+            .fields
+            .iter()
+            .map(|(key, value)| (*key, value as &dyn fmt::Display))
+            .collect();
+
+        self.logger.log(
+            tracing_level(metadata.level()),
+            metadata.target(),
+            metadata.file().zip(metadata.line()),
+            &visitor.message,
+            &fields,
+        );
+    }
+}
+
+/// Collects the fields of a `tracing` event.
+#[derive(Default)]
+struct EventVisitor {
+    message: String,
+    fields: Vec<(&'static str, String)>,
+}
+
+impl Visit for EventVisitor {
+    fn record_str(&mut self, field: &TracingField, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            self.fields.push((field.name(), value.to_string()));
+        }
+    }
+
+    fn record_debug(&mut self, field: &TracingField, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{:?}", value).ok();
+        } else {
+            self.fields.push((field.name(), format!("{:?}", value)));
+        }
+    }
+}
+
+/// A `log` logger that writes records to a [`Logger`].
+///
+/// Trace records are logged as debug messages.
+/// Records are filtered by the level of their target, falling back to the dependency level of the
+/// logger.
+#[derive(Debug)]
+pub struct LogBridge {
+    logger: &'static Logger,
+}
+
+impl LogBridge {
+    /// Creates a bridge that writes records to `logger`.
+    pub fn new(logger: &'static Logger) -> Self {
+        Self { logger }
+    }
+}
+
+fn log_level(level: log::Level) -> LogLevel {
+    match level {
+        log::Level::Trace | log::Level::Debug => LogLevel::Debug,
+        log::Level::Info => LogLevel::Info,
+        log::Level::Warn => LogLevel::Warn,
+        log::Level::Error => LogLevel::Error,
+    }
+}
+
+impl log::Log for LogBridge {
+    fn enabled(&self, metadata: &log::Metadata<'_>) -> bool {
+        self.logger
+            .dependency_enabled(metadata.target(), &log_level(metadata.level()))
+    }
+
+    fn log(&self, record: &log::Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        self.logger.log(
+            log_level(record.level()),
+            record.target(),
+            record.file().zip(record.line()),
+            &record.args().to_string(),
+            &[],
+        );
+    }
+
+    fn flush(&self) {
+        self.logger.flush();
+    }
+}
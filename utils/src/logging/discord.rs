@@ -0,0 +1,159 @@
+//! Sink that posts log records to a Discord channel.
+
+use super::LogLevel;
+use super::sink::{Record, Sink};
+use crate::EMBED_DESCRIPTION_LIMIT;
+use poise::serenity_prelude::{ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, Http};
+use std::io;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Receiver, Sender, error::TrySendError};
+
+/// The number of records waiting to be posted before new ones are dropped.
+const QUEUE_CAPACITY: usize = 256;
+
+/// The minimum time between two messages, records logged in between are batched.
+const BATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Posts log records to a Discord channel, batched into embeds.
+///
+/// Records are posted by a Tokio task. It waits [`BATCH_INTERVAL`] after the first record and
+/// then posts everything that arrived in the meantime, so at most one message is sent every few
+/// seconds. When more records arrive than can be posted, the oldest ones are kept and the number
+/// of dropped records is shown in the footer of the next embed.
+#[derive(Debug)]
+pub struct DiscordSink {
+    min_level: LogLevel,
+    sender: Sender<Record>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl DiscordSink {
+    /// Creates a sink that posts records of at least `min_level` to the channel and spawns the
+    /// task posting them. Must be called from within a Tokio runtime.
+    pub fn new(http: Arc<Http>, channel_id: ChannelId, min_level: LogLevel) -> Self {
+        let (sender, receiver) = mpsc::channel(QUEUE_CAPACITY);
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run(http, channel_id, receiver, dropped.clone()));
+
+        Self {
+            min_level,
+            sender,
+            dropped,
+        }
+    }
+}
+
+impl Sink for DiscordSink {
+    fn min_level(&self) -> LogLevel {
+        self.min_level.clone()
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self.sender.try_send(record.clone()) {
+            Ok(()) => Ok(()),
+            Err(TrySendError::Full(_)) => {
+                self.dropped.fetch_add(1, Ordering::Relaxed);
+                Ok(())
+            }
+            Err(TrySendError::Closed(_)) => Err(io::Error::new(
+                io::ErrorKind::BrokenPipe,
+                "Discord log task is not running",
+            )),
+        }
+    }
+}
+
+async fn run(
+    http: Arc<Http>,
+    channel_id: ChannelId,
+    mut receiver: Receiver<Record>,
+    dropped: Arc<AtomicU64>,
+) {
+    let mut pending: Vec<Record> = Vec::new();
+
+    loop {
+        if pending.is_empty() {
+            match receiver.recv().await {
+                Some(record) => pending.push(record),
+                None => return,
+            }
+        }
+
+        tokio::time::sleep(BATCH_INTERVAL).await;
+        while let Ok(record) = receiver.try_recv() {
+            if pending.len() < QUEUE_CAPACITY {
+                pending.push(record);
+            } else {
+                dropped.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+
+        let embed = take_batch(&mut pending, dropped.swap(0, Ordering::Relaxed));
+        let message = CreateMessage::new().embed(embed);
+        // Logging the error would post it to this channel again
+        if let Err(e) = channel_id.send_message(&http, message).await {
+            eprintln!("Failed to post logs to channel {}: {}", channel_id, e);
+        }
+    }
+}
+
+/// Removes as many records from the front of `pending` as fit into one embed and returns the
+/// embed.
+fn take_batch(pending: &mut Vec<Record>, dropped: u64) -> CreateEmbed {
+    let mut description = String::new();
+    let mut level = LogLevel::Debug;
+    let mut count = 0;
+
+    for record in pending.iter() {
+        let mut line = format!(
+            "`{}` **{}** {}",
+            record.timestamp.format("%H:%M:%S"),
+            record.level.name(),
+            record.message
+        );
+        for (key, value) in &record.fields {
+            line.push_str(&format!(" `{}={}`", key, value));
+        }
+        line = line.chars().take(EMBED_DESCRIPTION_LIMIT - 1).collect();
+
+        if count > 0
+            && description.chars().count() + line.chars().count() + 1 > EMBED_DESCRIPTION_LIMIT
+        {
+            break;
+        }
+
+        description.push_str(&line);
+        description.push('\n');
+        level = level.max(record.level.clone());
+        count += 1;
+    }
+
+    pending.drain(..count);
+
+    let color = match level {
+        LogLevel::Debug => 0x00FFFF,
+        LogLevel::Info => 0x00FF00,
+        LogLevel::Warn => 0xFFFF00,
+        LogLevel::Error | LogLevel::Off => 0xFF0000,
+    };
+
+    let mut embed = CreateEmbed::new()
+        .title("Logs")
+        .description(description)
+        .color(color);
+
+    let mut footer = Vec::new();
+    if !pending.is_empty() {
+        footer.push(format!("{} more records follow", pending.len()));
+    }
+    if dropped > 0 {
+        footer.push(format!("{} records dropped", dropped));
+    }
+    if !footer.is_empty() {
+        embed = embed.footer(CreateEmbedFooter::new(footer.join(", ")));
+    }
+
+    embed
+}
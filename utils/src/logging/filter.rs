@@ -0,0 +1,186 @@
+//! Log level filtering by target.
+
+use super::LogLevel;
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+/// Log levels per target, e.g. `warn,serenity=info,serenity::gateway=debug`.
+///
+/// A target is the module path a message comes from, like `serenity::gateway::shard`. The most
+/// specific matching directive wins, a directive for `serenity` also applies to
+/// `serenity::gateway`, but not to `serenity_extra`. Targets without a matching directive use the
+/// default level, which is set by a directive without a target.
+#[derive(Debug, Clone, Default, Eq, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct TargetFilter {
+    default: LogLevel,
+    directives: Vec<(String, LogLevel)>,
+}
+
+impl TargetFilter {
+    /// Creates a filter that uses `default` for every target.
+    pub fn new(default: LogLevel) -> Self {
+        Self {
+            default,
+            directives: Vec::new(),
+        }
+    }
+
+    /// Returns the filter with a directive for the target added, replacing an existing one.
+    pub fn with(mut self, target: impl Into<String>, level: LogLevel) -> Self {
+        self.set(target, level);
+        self
+    }
+
+    /// Sets the level of the target, replacing an existing directive.
+    pub fn set(&mut self, target: impl Into<String>, level: LogLevel) {
+        let target = target.into();
+        self.directives.retain(|(existing, _)| *existing != target);
+        self.directives.push((target, level));
+        // Longest targets first, so the first match is the most specific one
+        self.directives
+            .sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    }
+
+    /// Removes the directive for the target. Returns whether there was one.
+    pub fn remove(&mut self, target: &str) -> bool {
+        let len = self.directives.len();
+        self.directives.retain(|(existing, _)| existing != target);
+        self.directives.len() != len
+    }
+
+    /// Returns the level used for targets without a directive.
+    pub fn default_level(&self) -> &LogLevel {
+        &self.default
+    }
+
+    /// Sets the level used for targets without a directive.
+    pub fn set_default_level(&mut self, level: LogLevel) {
+        self.default = level;
+    }
+
+    /// Returns the directives, most specific first.
+    pub fn directives(&self) -> &[(String, LogLevel)] {
+        &self.directives
+    }
+
+    /// Returns the minimum level of messages from the target.
+    pub fn level_for(&self, target: &str) -> &LogLevel {
+        self.directive_for(target).unwrap_or(&self.default)
+    }
+
+    /// Returns the level of the most specific directive matching the target, if any.
+    pub fn directive_for(&self, target: &str) -> Option<&LogLevel> {
+        self.directives
+            .iter()
+            .find(|(prefix, _)| {
+                target
+                    .strip_prefix(prefix.as_str())
+                    .is_some_and(|rest| rest.is_empty() || rest.starts_with("::"))
+            })
+            .map(|(_, level)| level)
+    }
+
+    /// Applies comma separated directives like `info,commands::purge=debug` on top of this filter.
+    /// A level without a target replaces the default level. On error the filter is unchanged.
+    pub fn apply(&mut self, directives: &str) -> Result<(), ParseFilterError> {
+        let mut filter = self.clone();
+
+        for directive in directives
+            .split(',')
+            .map(str::trim)
+            .filter(|d| !d.is_empty())
+        {
+            match directive.split_once('=') {
+                Some((target, level)) => {
+                    let target = target.trim();
+                    if target.is_empty() {
+                        return Err(ParseFilterError(format!(
+                            "Missing target in '{}'",
+                            directive
+                        )));
+                    }
+                    filter.set(target, level.parse()?);
+                }
+                None => filter.default = directive.parse()?,
+            }
+        }
+
+        *self = filter;
+        Ok(())
+    }
+
+    /// Whether a message of the level from the target passes the filter.
+    pub fn enabled(&self, target: &str, level: &LogLevel) -> bool {
+        *level != LogLevel::Off && level >= self.level_for(target)
+    }
+
+    /// Returns the lowest level any target is enabled for.
+    pub fn min_level(&self) -> &LogLevel {
+        self.directives
+            .iter()
+            .map(|(_, level)| level)
+            .chain(std::iter::once(&self.default))
+            .min()
+            .unwrap_or(&self.default)
+    }
+}
+
+impl fmt::Display for TargetFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.default.name().to_lowercase())?;
+        for (target, level) in self.directives.iter().rev() {
+            write!(f, ",{}={}", target, level.name().to_lowercase())?;
+        }
+        Ok(())
+    }
+}
+
+/// Error returned when parsing a [`TargetFilter`] or [`LogLevel`] fails.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ParseFilterError(String);
+
+impl fmt::Display for ParseFilterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for ParseFilterError {}
+
+impl FromStr for LogLevel {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "trace" | "debug" => Ok(LogLevel::Debug),
+            "info" => Ok(LogLevel::Info),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "error" => Ok(LogLevel::Error),
+            "off" => Ok(LogLevel::Off),
+            other => Err(ParseFilterError(format!(
+                "Unknown log level '{}', expected debug, info, warn, error or off",
+                other
+            ))),
+        }
+    }
+}
+
+impl FromStr for TargetFilter {
+    type Err = ParseFilterError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut filter = TargetFilter::default();
+        filter.apply(s)?;
+        Ok(filter)
+    }
+}
+
+impl TryFrom<String> for TargetFilter {
+    type Error = ParseFilterError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
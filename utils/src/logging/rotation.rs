@@ -0,0 +1,266 @@
+//! Size and time based rotation of log files.
+
+use chrono::NaiveDate;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Defines when log files are rotated and how rotated files are kept.
+///
+/// The active log file keeps its name. When it is rotated, it is renamed to
+/// `<name>.<timestamp>.<extension>` and optionally compressed to `<...>.gz`. Rotated files are
+/// then pruned by age and total size.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RotationPolicy {
+    /// Rotate the file before it grows larger than this many bytes. `None` disables size based
+    /// rotation.
+    pub max_size: Option<u64>,
+    /// Rotate the file when the first message of a new day (in the logging timezone) is written.
+    pub daily: bool,
+    /// Compress rotated files with gzip.
+    pub compress: bool,
+    /// Delete rotated files older than this. `None` keeps them regardless of age.
+    pub max_age: Option<Duration>,
+    /// Delete the oldest rotated files while all log files together are larger than this many
+    /// bytes. The active file is never deleted. `None` disables the limit.
+    pub max_total_size: Option<u64>,
+}
+
+/// A log file that is rotated according to a [`RotationPolicy`]. Writes are buffered until
+/// [`RotatingFile::flush`] is called or the file is rotated.
+#[derive(Debug)]
+pub(crate) struct RotatingFile {
+    path: PathBuf,
+    policy: RotationPolicy,
+    timezone: chrono_tz::Tz,
+    file: Option<BufWriter<File>>,
+    size: u64,
+    day: Option<NaiveDate>,
+}
+
+impl RotatingFile {
+    pub fn new(path: impl Into<PathBuf>, policy: RotationPolicy, timezone: chrono_tz::Tz) -> Self {
+        Self {
+            path: path.into(),
+            policy,
+            timezone,
+            file: None,
+            size: 0,
+            day: None,
+        }
+    }
+
+    /// Writes a line to the file, rotating it first if necessary.
+    pub fn write_line(&mut self, line: &str) -> io::Result<()> {
+        let today = self.today();
+        let incoming = line.len() as u64 + 1;
+
+        if self.file.is_none() {
+            self.open()?;
+        }
+
+        if self.should_rotate(incoming, today) {
+            self.rotate()?;
+            self.open()?;
+        }
+
+        if let Some(file) = &mut self.file {
+            writeln!(file, "{}", line)?;
+            self.size += incoming;
+            self.day = Some(today);
+        }
+
+        Ok(())
+    }
+
+    /// Writes buffered lines to the file.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    fn today(&self) -> NaiveDate {
+        chrono::Utc::now()
+            .with_timezone(&self.timezone)
+            .date_naive()
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if let Some(parent) = self.path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        let metadata = file.metadata()?;
+
+        self.size = metadata.len();
+        // An existing file belongs to the day it was last written to
+        self.day = metadata
+            .modified()
+            .ok()
+            .filter(|_| self.size > 0)
+            .map(|modified| {
+                chrono::DateTime::<chrono::Utc>::from(modified)
+                    .with_timezone(&self.timezone)
+                    .date_naive()
+            });
+        self.file = Some(BufWriter::new(file));
+
+        Ok(())
+    }
+
+    fn should_rotate(&self, incoming: u64, today: NaiveDate) -> bool {
+        if self.size == 0 {
+            return false;
+        }
+
+        let new_day = self.policy.daily && self.day.is_some_and(|day| day != today);
+        let too_large = self
+            .policy
+            .max_size
+            .is_some_and(|max_size| self.size + incoming > max_size);
+
+        new_day || too_large
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        // Close the file before renaming it
+        if let Some(mut file) = self.file.take() {
+            file.flush()?;
+        }
+
+        let timestamp = chrono::Utc::now()
+            .with_timezone(&self.timezone)
+            .format("%Y-%m-%d_%H-%M-%S");
+        let (stem, extension) = name_parts(&self.path);
+        let mut rotated = self
+            .path
+            .with_file_name(format!("{}.{}{}", stem, timestamp, extension));
+
+        // Multiple rotations within the same second must not overwrite each other
+        let mut i = 1;
+        while rotated.exists() || gz_path(&rotated).exists() {
+            rotated = self
+                .path
+                .with_file_name(format!("{}.{}-{}{}", stem, timestamp, i, extension));
+            i += 1;
+        }
+
+        std::fs::rename(&self.path, &rotated)?;
+
+        if self.policy.compress {
+            compress(&rotated)?;
+        }
+
+        self.prune()
+    }
+
+    /// Deletes rotated files that are too old or exceed the total size limit.
+    fn prune(&self) -> io::Result<()> {
+        if self.policy.max_age.is_none() && self.policy.max_total_size.is_none() {
+            return Ok(());
+        }
+
+        let active_size = std::fs::metadata(&self.path).map(|m| m.len()).unwrap_or(0);
+        let rotated = rotated_files(&self.path)?;
+
+        let now = SystemTime::now();
+        let mut total_size: u64 =
+            active_size + rotated.iter().map(|(_, _, size)| size).sum::<u64>();
+
+        for (path, modified, size) in rotated {
+            let too_old = self
+                .policy
+                .max_age
+                .is_some_and(|max_age| now.duration_since(modified).is_ok_and(|age| age > max_age));
+            let too_large = self
+                .policy
+                .max_total_size
+                .is_some_and(|max_total_size| total_size > max_total_size);
+
+            if too_old || too_large {
+                std::fs::remove_file(&path)?;
+                total_size -= size;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Splits the file name into the stem and the extension including the dot.
+fn name_parts(path: &Path) -> (String, String) {
+    let stem = path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let extension = path
+        .extension()
+        .map(|extension| format!(".{}", extension.to_string_lossy()))
+        .unwrap_or_default();
+    (stem, extension)
+}
+
+/// Returns the rotated files of the log file at `path` with their modification time and size,
+/// oldest first.
+pub(crate) fn rotated_files(path: &Path) -> io::Result<Vec<(PathBuf, SystemTime, u64)>> {
+    let Some(directory) = path.parent() else {
+        return Ok(Vec::new());
+    };
+    let directory = if directory.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        directory
+    };
+
+    let (stem, _) = name_parts(path);
+    let prefix = format!("{}.", stem);
+
+    let mut rotated: Vec<(PathBuf, SystemTime, u64)> = Vec::new();
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let entry_path = entry.path();
+        let is_rotated = entry
+            .file_name()
+            .to_str()
+            .is_some_and(|name| name.starts_with(&prefix));
+        if !is_rotated || entry_path.file_name() == path.file_name() {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if metadata.is_file() {
+            let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+            rotated.push((entry_path, modified, metadata.len()));
+        }
+    }
+
+    // Oldest first
+    rotated.sort_by_key(|(_, modified, _)| *modified);
+    Ok(rotated)
+}
+
+fn gz_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".gz");
+    PathBuf::from(name)
+}
+
+/// Compresses the file to `<path>.gz` and deletes the original.
+fn compress(path: &Path) -> io::Result<()> {
+    let mut input = File::open(path)?;
+    let output = File::create(gz_path(path))?;
+    let mut encoder = GzEncoder::new(output, Compression::default());
+    io::copy(&mut input, &mut encoder)?;
+    encoder.finish()?;
+    std::fs::remove_file(path)
+}
@@ -0,0 +1,222 @@
+//! Reading and searching the log files written by a [`Logger`](super::Logger).
+
+use super::LogLevel;
+use super::rotation::rotated_files;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use flate2::read::GzDecoder;
+use lazy_static::lazy_static;
+use regex::Regex;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::path::Path;
+use std::time::SystemTime;
+
+lazy_static! {
+    static ref TIMESTAMP_REGEX: Regex =
+        Regex::new(r"(\d{4}-\d{2}-\d{2})[ T](\d{2}:\d{2}:\d{2})").unwrap();
+}
+
+/// A logged message read back from a log file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct LogEntry {
+    /// When the message was logged, if the line has a recognizable timestamp
+    pub timestamp: Option<DateTime<Tz>>,
+    /// The level of the message, if the line contains one
+    pub level: Option<LogLevel>,
+    /// The line as written to the file. Messages spanning several lines keep their line breaks.
+    pub text: String,
+}
+
+/// Filters for [`Logger::search`](super::Logger::search). An entry has to match every set filter.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct LogQuery {
+    /// Only entries of at least this level
+    pub min_level: Option<LogLevel>,
+    /// Only entries logged at or after this time
+    pub since: Option<DateTime<Tz>>,
+    /// Only entries logged at or before this time
+    pub until: Option<DateTime<Tz>>,
+    /// Only entries containing this text, ignoring case
+    pub contains: Option<String>,
+    /// Whether rotated files are searched as well as the active file
+    pub include_rotated: bool,
+}
+
+impl LogQuery {
+    fn matches(&self, entry: &LogEntry) -> bool {
+        if let Some(min_level) = &self.min_level
+            && entry.level.as_ref().is_none_or(|level| level < min_level)
+        {
+            return false;
+        }
+        if let Some(since) = &self.since
+            && entry.timestamp.is_none_or(|timestamp| timestamp < *since)
+        {
+            return false;
+        }
+        if let Some(until) = &self.until
+            && entry.timestamp.is_none_or(|timestamp| timestamp > *until)
+        {
+            return false;
+        }
+        if let Some(contains) = &self.contains
+            && !entry.text.to_lowercase().contains(&contains.to_lowercase())
+        {
+            return false;
+        }
+        true
+    }
+}
+
+/// Returns the newest `limit` entries of the log file at `path` and, if requested, its rotated
+/// files that match the query, oldest first.
+pub(crate) fn search(
+    path: &Path,
+    query: &LogQuery,
+    limit: usize,
+    timezone: Tz,
+) -> io::Result<Vec<LogEntry>> {
+    let mut files = Vec::new();
+    if query.include_rotated {
+        let since = query.since.map(SystemTime::from);
+        for (rotated, modified, _) in rotated_files(path)? {
+            // The file was closed before the first entry we are looking for
+            if since.is_some_and(|since| modified < since) {
+                continue;
+            }
+            files.push(rotated);
+        }
+    }
+    if path.exists() {
+        files.push(path.to_path_buf());
+    }
+
+    let mut results = VecDeque::new();
+    for file in files {
+        let reader: Box<dyn Read> = if file.extension().is_some_and(|e| e == "gz") {
+            Box::new(GzDecoder::new(File::open(&file)?))
+        } else {
+            Box::new(File::open(&file)?)
+        };
+
+        let mut current: Option<LogEntry> = None;
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            match parse_line(&line, timezone) {
+                Some(entry) => {
+                    if let Some(finished) = current.replace(entry) {
+                        push_result(&mut results, finished, query, limit);
+                    }
+                }
+                None => match &mut current {
+                    Some(entry) => {
+                        entry.text.push('\n');
+                        entry.text.push_str(&line);
+                    }
+                    None => {
+                        current = Some(LogEntry {
+                            timestamp: None,
+                            level: None,
+                            text: line,
+                        })
+                    }
+                },
+            }
+        }
+        if let Some(finished) = current {
+            push_result(&mut results, finished, query, limit);
+        }
+    }
+
+    Ok(results.into())
+}
+
+fn push_result(results: &mut VecDeque<LogEntry>, entry: LogEntry, query: &LogQuery, limit: usize) {
+    if limit == 0 || !query.matches(&entry) {
+        return;
+    }
+    if results.len() == limit {
+        results.pop_front();
+    }
+    results.push_back(entry);
+}
+
+/// Parses a line that starts a new entry. Returns `None` for continuation lines, which have no
+/// timestamp.
+fn parse_line(line: &str, timezone: Tz) -> Option<LogEntry> {
+    if line.starts_with('{')
+        && let Ok(serde_json::Value::Object(object)) = serde_json::from_str(line)
+    {
+        let timestamp = object
+            .get("timestamp")
+            .and_then(|timestamp| timestamp.as_str())
+            .and_then(|timestamp| DateTime::parse_from_rfc3339(timestamp).ok())
+            .map(|timestamp| timestamp.with_timezone(&timezone));
+        let level = object
+            .get("level")
+            .and_then(|level| level.as_str())
+            .and_then(|level| level.parse().ok());
+        return Some(LogEntry {
+            timestamp,
+            level,
+            text: line.to_string(),
+        });
+    }
+
+    let captures = TIMESTAMP_REGEX.captures(line)?;
+    let naive = NaiveDateTime::parse_from_str(
+        &format!("{} {}", &captures[1], &captures[2]),
+        "%Y-%m-%d %H:%M:%S",
+    )
+    .ok()?;
+    // The first level name after the timestamp, e.g. `[INFO]`
+    let level = line[captures.get(0)?.end()..]
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .find_map(|word| match word {
+            "DEBUG" => Some(LogLevel::Debug),
+            "INFO" => Some(LogLevel::Info),
+            "WARN" => Some(LogLevel::Warn),
+            "ERROR" => Some(LogLevel::Error),
+            _ => None,
+        });
+
+    Some(LogEntry {
+        timestamp: timezone.from_local_datetime(&naive).earliest(),
+        level,
+        text: line.to_string(),
+    })
+}
+
+/// Parses a point in time like `2h` (two hours ago), `2024-05-01` or `2024-05-01 13:37` in the
+/// timezone. Supported units for relative times are `m`, `h`, `d` and `w`.
+pub fn parse_time(input: &str, timezone: Tz) -> Option<DateTime<Tz>> {
+    let input = input.trim();
+
+    if let Some(split) = input.find(|c: char| !c.is_ascii_digit())
+        && split > 0
+    {
+        let amount: i64 = input[..split].parse().ok()?;
+        let delta = match input[split..].trim() {
+            "m" => TimeDelta::try_minutes(amount),
+            "h" => TimeDelta::try_hours(amount),
+            "d" => TimeDelta::try_days(amount),
+            "w" => TimeDelta::try_weeks(amount),
+            _ => None,
+        };
+        if let Some(delta) = delta {
+            return Some(chrono::Utc::now().with_timezone(&timezone) - delta);
+        }
+    }
+
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(input, format).ok())
+        .or_else(|| {
+            NaiveDate::parse_from_str(input, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })?;
+    timezone.from_local_datetime(&naive).earliest()
+}
@@ -0,0 +1,146 @@
+//! Destinations for log records.
+
+use super::rotation::{RotatingFile, RotationPolicy};
+use super::{FileFormat, LogLevel};
+use std::io::{self, BufWriter, Stdout, Write};
+use std::path::PathBuf;
+
+/// A log message with everything sinks need to write it.
+#[derive(Debug, Clone)]
+pub struct Record {
+    /// The level of the message
+    pub level: LogLevel,
+    /// The module path the message was logged from, e.g. `commands::purge`. Empty for the crate
+    /// root.
+    pub target: String,
+    /// When the message was logged, in the timezone of the logging format
+    pub timestamp: chrono::DateTime<chrono_tz::Tz>,
+    /// The message itself
+    pub message: String,
+    /// Key/value pairs attached at the call site
+    pub fields: Vec<(String, String)>,
+    /// The message rendered with the logging template, including ANSI codes
+    pub terminal: String,
+    /// The message rendered with the logging template without ANSI codes, followed by the fields
+    /// as `key=value` pairs
+    pub plain: String,
+}
+
+impl Record {
+    /// Renders the record as a single line JSON object with the keys `timestamp` (RFC 3339),
+    /// `level`, `target` (if not empty) and `message`, followed by the fields. Fields named like
+    /// one of these keys are ignored.
+    pub fn to_json(&self) -> String {
+        let mut object = serde_json::Map::new();
+        object.insert(
+            String::from("timestamp"),
+            self.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Millis, true)
+                .into(),
+        );
+        object.insert(String::from("level"), self.level.name().into());
+        if !self.target.is_empty() {
+            object.insert(String::from("target"), self.target.as_str().into());
+        }
+        object.insert(String::from("message"), self.message.as_str().into());
+        for (key, value) in &self.fields {
+            object
+                .entry(key.as_str())
+                .or_insert_with(|| value.as_str().into());
+        }
+        serde_json::Value::Object(object).to_string()
+    }
+}
+
+/// A destination for log records, like the terminal, a file or a Discord channel.
+///
+/// Sinks are driven by the background writer thread of the [`Logger`](super::Logger), so
+/// `write` may block, but slow sinks delay all other sinks. Errors are reported to stderr.
+pub trait Sink: Send {
+    /// Records below this level are not passed to the sink.
+    fn min_level(&self) -> LogLevel {
+        LogLevel::Debug
+    }
+
+    /// Writes a single record.
+    fn write(&mut self, record: &Record) -> io::Result<()>;
+
+    /// Writes buffered records. Called whenever the writer queue is empty.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Writes the colored template to stdout.
+#[derive(Debug)]
+pub struct StdoutSink {
+    min_level: LogLevel,
+    stdout: BufWriter<Stdout>,
+}
+
+impl StdoutSink {
+    /// Creates a sink that writes records of at least `min_level` to stdout.
+    pub fn new(min_level: LogLevel) -> Self {
+        Self {
+            min_level,
+            stdout: BufWriter::new(io::stdout()),
+        }
+    }
+}
+
+impl Sink for StdoutSink {
+    fn min_level(&self) -> LogLevel {
+        self.min_level.clone()
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        writeln!(self.stdout, "{}", record.terminal)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+/// Writes records to a file, which is rotated according to a [`RotationPolicy`].
+#[derive(Debug)]
+pub struct FileSink {
+    min_level: LogLevel,
+    format: FileFormat,
+    file: RotatingFile,
+}
+
+impl FileSink {
+    /// Creates a sink that writes records of at least `min_level` to the file at `path`. The
+    /// file and its parent directories are created when the first record is written.
+    pub fn new(
+        path: impl Into<PathBuf>,
+        min_level: LogLevel,
+        format: FileFormat,
+        rotation: RotationPolicy,
+        timezone: chrono_tz::Tz,
+    ) -> Self {
+        Self {
+            min_level,
+            format,
+            file: RotatingFile::new(path, rotation, timezone),
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn min_level(&self) -> LogLevel {
+        self.min_level.clone()
+    }
+
+    fn write(&mut self, record: &Record) -> io::Result<()> {
+        match self.format {
+            FileFormat::Template => self.file.write_line(&record.plain),
+            FileFormat::Json => self.file.write_line(&record.to_json()),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
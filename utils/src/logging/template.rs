@@ -0,0 +1,206 @@
+//! Parsing of logging templates.
+
+use super::{COLOR_REGEX, Color};
+use std::fmt;
+
+/// A value inserted into the template.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub(crate) enum Placeholder {
+    Timestamp,
+    Level,
+    Message,
+    Target,
+    File,
+    Line,
+    Thread,
+    Elapsed,
+    Fields,
+}
+
+impl Placeholder {
+    /// Returns the placeholder and whether it is colored, e.g. `levelc` is a colored level.
+    fn parse(name: &str) -> Option<(Self, bool)> {
+        let placeholder = match name {
+            "timestamp" | "timestampc" => Self::Timestamp,
+            "level" | "levelc" => Self::Level,
+            "message" | "messagec" => Self::Message,
+            "target" => Self::Target,
+            "file" => Self::File,
+            "line" => Self::Line,
+            "thread" => Self::Thread,
+            "elapsed" => Self::Elapsed,
+            "fields" => Self::Fields,
+            _ => return None,
+        };
+        Some((placeholder, name.ends_with('c')))
+    }
+}
+
+/// A part of a parsed template.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) enum Token {
+    /// Literal text
+    Text(String),
+    /// A placeholder, colored if the flag is set
+    Placeholder(Placeholder, bool),
+    /// Toggles bold text
+    Bold,
+    /// Toggles italic text
+    Italic,
+    /// Starts colored text
+    ColorStart(Color),
+    /// Ends colored text
+    ColorEnd,
+}
+
+/// A logging template parsed into tokens.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct Template {
+    tokens: Vec<Token>,
+}
+
+impl Template {
+    /// Parses the template. See [`LoggingFormat`](super::LoggingFormat) for the syntax.
+    pub(crate) fn parse(template: &str) -> Result<Self, TemplateError> {
+        let chars: Vec<char> = template.chars().collect();
+        let mut tokens = Vec::new();
+        let mut text = String::new();
+        let mut bold = None;
+        let mut italic = None;
+        let mut colored = None;
+
+        let mut i = 0;
+        while i < chars.len() {
+            let c = chars[i];
+            let markup = match c {
+                '\\' => {
+                    // Escaped character, a trailing backslash is kept as it is
+                    text.push(chars.get(i + 1).copied().unwrap_or(c));
+                    i += 2;
+                    continue;
+                }
+                '{' if chars.get(i + 1) == Some(&'{') => {
+                    let end = chars[i + 2..]
+                        .windows(2)
+                        .position(|window| window == ['}', '}'])
+                        .map(|j| j + i + 2)
+                        .ok_or(TemplateError::UnclosedPlaceholder(i))?;
+                    let name: String = chars[i + 2..end].iter().collect();
+                    let (placeholder, colored) = Placeholder::parse(name.trim())
+                        .ok_or(TemplateError::UnknownPlaceholder(name, i))?;
+
+                    i = end + 2;
+                    Token::Placeholder(placeholder, colored)
+                }
+                '*' => {
+                    bold = if bold.is_some() { None } else { Some(i) };
+                    i += 1;
+                    Token::Bold
+                }
+                '_' => {
+                    italic = if italic.is_some() { None } else { Some(i) };
+                    i += 1;
+                    Token::Italic
+                }
+                '~' if colored.is_some() => {
+                    colored = None;
+                    i += 1;
+                    Token::ColorEnd
+                }
+                '~' => {
+                    let hex_code: String = chars.iter().skip(i + 1).take(7).collect();
+                    if !COLOR_REGEX.is_match(&hex_code) {
+                        return Err(TemplateError::InvalidColor(i));
+                    }
+                    // Unwrap is safe due to regex check
+                    let color = Color::hex(&hex_code).unwrap();
+
+                    colored = Some(i);
+                    i += 8;
+                    Token::ColorStart(color)
+                }
+                _ => {
+                    text.push(c);
+                    i += 1;
+                    continue;
+                }
+            };
+
+            if !text.is_empty() {
+                tokens.push(Token::Text(std::mem::take(&mut text)));
+            }
+            tokens.push(markup);
+        }
+
+        if !text.is_empty() {
+            tokens.push(Token::Text(text));
+        }
+
+        if let Some(position) = bold {
+            return Err(TemplateError::Unclosed('*', position));
+        }
+        if let Some(position) = italic {
+            return Err(TemplateError::Unclosed('_', position));
+        }
+        if let Some(position) = colored {
+            return Err(TemplateError::Unclosed('~', position));
+        }
+
+        Ok(Self { tokens })
+    }
+
+    /// Returns the tokens in order.
+    pub(crate) fn tokens(&self) -> &[Token] {
+        &self.tokens
+    }
+
+    /// Whether the template places the fields itself.
+    pub(crate) fn has_fields(&self) -> bool {
+        self.tokens
+            .iter()
+            .any(|token| matches!(token, Token::Placeholder(Placeholder::Fields, _)))
+    }
+}
+
+/// Error returned when a logging template is invalid. Positions are character offsets into the
+/// template.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TemplateError {
+    /// A placeholder that does not exist, e.g. `{{user}}`.
+    UnknownPlaceholder(String, usize),
+    /// A `{{` without a matching `}}`.
+    UnclosedPlaceholder(usize),
+    /// A `~` that is not followed by a `#RRGGBB` color.
+    InvalidColor(usize),
+    /// A `*`, `_` or `~` that is never closed.
+    Unclosed(char, usize),
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownPlaceholder(name, position) => write!(
+                f,
+                "Unknown placeholder '{{{{{}}}}}' at position {}",
+                name, position
+            ),
+            Self::UnclosedPlaceholder(position) => {
+                write!(f, "Missing '}}}}' for the '{{{{' at position {}", position)
+            }
+            Self::InvalidColor(position) => write!(
+                f,
+                "Expected a color like '#RRGGBB' after the '~' at position {}",
+                position
+            ),
+            Self::Unclosed(markup, position) => {
+                write!(
+                    f,
+                    "The '{}' at position {} is never closed",
+                    markup, position
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
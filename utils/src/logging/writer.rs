@@ -0,0 +1,217 @@
+//! Background thread that passes log records to the sinks.
+
+use super::sink::{Record, Sink};
+use serde::Deserialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender, TrySendError};
+use std::time::Duration;
+
+/// What happens to a log message when the buffer of the background writer is full.
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OverflowPolicy {
+    /// Drop the message silently
+    Drop,
+    /// Wait until there is space in the buffer. Blocks the logging thread.
+    Block,
+    /// Drop the message and log how many messages were dropped once there is space again
+    #[default]
+    Report,
+}
+
+enum Command {
+    Record(Box<Record>),
+    AddSink(Box<dyn Sink>),
+    SetFile(Option<Box<dyn Sink>>),
+    Flush(SyncSender<()>),
+}
+
+/// Handle to the background writer thread. The thread exits after writing all queued records
+/// once the handle is dropped.
+#[derive(Debug)]
+pub(crate) struct Writer {
+    sender: Option<SyncSender<Command>>,
+    policy: OverflowPolicy,
+    dropped: AtomicU64,
+    unreported: AtomicU64,
+}
+
+impl Writer {
+    /// Spawns the writer thread with a buffer for `capacity` messages. The file sink is kept
+    /// separately from the other sinks, so it can be replaced.
+    pub fn spawn(
+        sinks: Vec<Box<dyn Sink>>,
+        file: Option<Box<dyn Sink>>,
+        capacity: usize,
+        policy: OverflowPolicy,
+    ) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity.max(1));
+        let spawned = std::thread::Builder::new()
+            .name(String::from("log-writer"))
+            .spawn(move || run(receiver, sinks, file));
+
+        let sender = match spawned {
+            Ok(_) => Some(sender),
+            Err(e) => {
+                eprintln!("Failed to start log writer, only logging to stdout: {}", e);
+                None
+            }
+        };
+
+        Self {
+            sender,
+            policy,
+            dropped: AtomicU64::new(0),
+            unreported: AtomicU64::new(0),
+        }
+    }
+
+    /// Queues a record according to the overflow policy. If the writer is not running, the record
+    /// is printed to stdout directly. Returns false if the record was dropped.
+    pub fn write(&self, record: Record) -> bool {
+        let queued = self.send(record, self.policy);
+        if !queued {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+            if self.policy == OverflowPolicy::Report {
+                self.unreported.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        queued
+    }
+
+    /// Queues the report about dropped records without waiting. Returns false if the buffer is
+    /// still full.
+    pub fn write_report(&self, record: Record) -> bool {
+        self.send(record, OverflowPolicy::Drop)
+    }
+
+    fn send(&self, record: Record, policy: OverflowPolicy) -> bool {
+        let Some(sender) = &self.sender else {
+            println!("{}", record.terminal);
+            return true;
+        };
+
+        let command = Command::Record(Box::new(record));
+        let result = match policy {
+            OverflowPolicy::Block => sender
+                .send(command)
+                .map_err(|e| TrySendError::Disconnected(e.0)),
+            OverflowPolicy::Drop | OverflowPolicy::Report => sender.try_send(command),
+        };
+
+        match result {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => false,
+            Err(TrySendError::Disconnected(command)) => {
+                if let Command::Record(record) = command {
+                    println!("{}", record.terminal);
+                }
+                true
+            }
+        }
+    }
+
+    /// Adds a sink. Records queued before are not passed to it.
+    pub fn add_sink(&self, sink: Box<dyn Sink>) {
+        if let Some(sender) = &self.sender {
+            sender.send(Command::AddSink(sink)).ok();
+        }
+    }
+
+    /// Replaces the file sink. Records queued before are still written to the previous file.
+    pub fn set_file(&self, file: Option<Box<dyn Sink>>) {
+        if let Some(sender) = &self.sender {
+            sender.send(Command::SetFile(file)).ok();
+        }
+    }
+
+    /// Waits until all queued records are written and flushed, at most for `timeout`. Returns
+    /// whether the flush finished in time.
+    pub fn flush(&self, timeout: Duration) -> bool {
+        let Some(sender) = &self.sender else {
+            return true;
+        };
+
+        let (ack_sender, ack_receiver) = mpsc::sync_channel(1);
+        if sender.send(Command::Flush(ack_sender)).is_err() {
+            return false;
+        }
+        ack_receiver.recv_timeout(timeout).is_ok()
+    }
+
+    /// Returns the number of records dropped because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of dropped records that still have to be reported and resets it.
+    pub fn take_unreported(&self) -> u64 {
+        if self.unreported.load(Ordering::Relaxed) == 0 {
+            return 0;
+        }
+        self.unreported.swap(0, Ordering::Relaxed)
+    }
+
+    /// Adds dropped records that could not be reported yet.
+    pub fn restore_unreported(&self, count: u64) {
+        self.unreported.fetch_add(count, Ordering::Relaxed);
+    }
+}
+
+fn run(
+    receiver: Receiver<Command>,
+    mut sinks: Vec<Box<dyn Sink>>,
+    mut file: Option<Box<dyn Sink>>,
+) {
+    // Ends when all senders are dropped
+    while let Ok(command) = receiver.recv() {
+        let mut acks = Vec::new();
+        // Write everything that is queued before flushing
+        let mut next = Some(command);
+        while let Some(command) = next {
+            match command {
+                Command::Record(record) => {
+                    for sink in sinks.iter_mut().chain(file.iter_mut()) {
+                        write(sink.as_mut(), &record);
+                    }
+                }
+                Command::AddSink(sink) => sinks.push(sink),
+                Command::SetFile(new_file) => {
+                    if let Some(previous) = &mut file {
+                        flush(previous.as_mut());
+                    }
+                    file = new_file;
+                }
+                Command::Flush(ack) => acks.push(ack),
+            }
+            next = receiver.try_recv().ok();
+        }
+
+        for sink in sinks.iter_mut().chain(file.iter_mut()) {
+            flush(sink.as_mut());
+        }
+        for ack in acks {
+            ack.send(()).ok();
+        }
+    }
+
+    for sink in sinks.iter_mut().chain(file.iter_mut()) {
+        flush(sink.as_mut());
+    }
+}
+
+/// Passes the record to the sink if its level is high enough. Errors are reported to stderr
+/// instead of being returned, logging must never take the bot down.
+fn write(sink: &mut dyn Sink, record: &Record) {
+    if record.level >= sink.min_level()
+        && let Err(e) = sink.write(record)
+    {
+        eprintln!("Failed to write log record: {}", e);
+    }
+}
+
+fn flush(sink: &mut dyn Sink) {
+    if let Err(e) = sink.flush() {
+        eprintln!("Failed to flush log sink: {}", e);
+    }
+}
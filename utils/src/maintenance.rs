@@ -0,0 +1,57 @@
+//! Maintenance mode, in which only owners can use commands.
+
+use crate::config::Config;
+use crate::shared_types::{Context, Error};
+use crate::storage::{Scope, Storage, StorageError};
+use poise::CreateReply;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The key the state is stored under in the global scope, so it survives restarts.
+const STORAGE_KEY: &str = "maintenance";
+
+/// Whether the bot is in maintenance mode. Part of the shared [`Data`](crate::shared_types::Data).
+#[derive(Debug)]
+pub struct Maintenance {
+    enabled: AtomicBool,
+}
+
+impl Maintenance {
+    /// Loads the stored state. Maintenance mode is off if nothing is stored.
+    pub fn load(storage: &Storage) -> Result<Self, StorageError> {
+        let enabled = storage
+            .kv()
+            .get(Scope::Global, STORAGE_KEY)?
+            .unwrap_or(false);
+        Ok(Self {
+            enabled: AtomicBool::new(enabled),
+        })
+    }
+
+    /// Whether the bot is in maintenance mode.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::SeqCst)
+    }
+
+    /// Turns maintenance mode on or off and stores the state.
+    pub fn set(&self, storage: &Storage, enabled: bool) -> Result<(), StorageError> {
+        storage.kv().set(Scope::Global, STORAGE_KEY, &enabled)?;
+        self.enabled.store(enabled, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// Replies with the maintenance message to commands of non-owners while maintenance mode is on.
+/// Used as part of the global command check.
+pub(crate) async fn check(ctx: Context<'_>, config: &Config) -> Result<bool, Error> {
+    if !ctx.data().maintenance.is_enabled()
+        || ctx.framework().options().owners.contains(&ctx.author().id)
+    {
+        return Ok(true);
+    }
+
+    let reply = CreateReply::default()
+        .content(config.settings().maintenance_message.clone())
+        .ephemeral(true);
+    ctx.send(reply).await.ok();
+    Ok(false)
+}
@@ -1,7 +1,18 @@
 //! Contains shared types
 
+use crate::cooldown::Cooldowns;
+use crate::maintenance::Maintenance;
+use crate::storage::Storage;
+
 /// The shared data for the bot.
-pub struct Data {}
+pub struct Data {
+    /// The persistent storage
+    pub storage: Storage,
+    /// Whether the bot is in maintenance mode
+    pub maintenance: Maintenance,
+    /// When the cooldowns of the commands end
+    pub cooldowns: Cooldowns,
+}
 
 /// The shared error type for the bot.
 pub type Error = Box<dyn std::error::Error + Send + Sync>;
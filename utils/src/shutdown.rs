@@ -0,0 +1,145 @@
+//! Graceful shutdown of the bot, shared by the `/shutdown` and `/restart` commands and the signal
+//! handler.
+
+use crate::config::Config;
+use crate::shared_types::{Context, Error};
+use poise::CreateReply;
+use poise::serenity_prelude::{CreateEmbed, CreateMessage, Http, OnlineStatus, ShardManager};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+/// The exit status of the process after `/restart`, so a supervisor like systemd or docker can
+/// tell restarts from shutdowns and relaunch the bot.
+pub const RESTART_EXIT_CODE: u8 = 75;
+
+/// How long the shutdown waits for running commands to finish.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often the shutdown checks whether the running commands finished.
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+static RESTARTING: AtomicBool = AtomicBool::new(false);
+
+/// The IDs of the running command invocations.
+static RUNNING: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+/// Whether the bot is shutting down. New commands are rejected from then on.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Whether the bot is shutting down to be restarted. The process should exit with
+/// [`RESTART_EXIT_CODE`] then.
+pub fn is_restarting() -> bool {
+    RESTARTING.load(Ordering::SeqCst)
+}
+
+/// Why the bot shuts down.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum ShutdownKind {
+    /// The bot stops and stays offline
+    Shutdown,
+    /// The bot stops and is expected to be relaunched by a supervisor
+    Restart,
+}
+
+/// Marks the invocation as running. Called before every command.
+pub(crate) fn command_started(id: u64) {
+    running().push(id);
+}
+
+/// Marks the invocation as finished. Called after every command and every error of a command,
+/// so unknown IDs are ignored.
+pub(crate) fn command_finished(id: u64) {
+    running().retain(|running| *running != id);
+}
+
+fn running() -> std::sync::MutexGuard<'static, Vec<u64>> {
+    RUNNING.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Rejects commands while the bot is shutting down. Used as the global command check.
+pub(crate) async fn check(ctx: Context<'_>) -> Result<bool, Error> {
+    if !is_shutting_down() {
+        return Ok(true);
+    }
+
+    let reply = CreateReply::default()
+        .content(":x: The bot is shutting down, try again later!")
+        .ephemeral(true);
+    ctx.send(reply).await.ok();
+    Ok(false)
+}
+
+/// Shuts the bot down: sets the presence to invisible, posts the "Bot offline!" or "Bot
+/// restarting!" embed with the reason to the shutdown channel of every guild, waits up to
+/// [`DRAIN_TIMEOUT`] for running commands to finish and stops all shards, which makes the client
+/// return.
+///
+/// Only the first call has an effect. Must not be awaited from within a command, as it waits for
+/// that command to finish, spawn a task instead.
+pub async fn shutdown(
+    http: &Http,
+    shard_manager: &ShardManager,
+    config: &Config,
+    kind: ShutdownKind,
+    reason: &str,
+) {
+    if SHUTTING_DOWN.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let (title, color) = match kind {
+        ShutdownKind::Shutdown => {
+            config.logger.info(&format!("Shutting down: {}", reason));
+            ("Bot offline!", 0xFF0000)
+        }
+        ShutdownKind::Restart => {
+            RESTARTING.store(true, Ordering::SeqCst);
+            config.logger.info(&format!("Restarting: {}", reason));
+            ("Bot restarting!", 0xFFA500)
+        }
+    };
+
+    for runner in shard_manager.runners.lock().await.values() {
+        runner.runner_tx.set_presence(None, OnlineStatus::Invisible);
+    }
+
+    let embed = CreateEmbed::new()
+        .title(title)
+        .description(reason)
+        .color(color);
+    let message = CreateMessage::default().embed(embed);
+    for (guild_id, guild) in &config.settings().guilds {
+        if let Err(e) = guild
+            .shutdown_channel
+            .send_message(http, message.clone())
+            .await
+        {
+            config.logger.error(&format!(
+                "Failed to post shutdown message in guild {}: {}",
+                guild_id, e
+            ));
+        }
+    }
+
+    let start = Instant::now();
+    loop {
+        let count = running().len();
+        if count == 0 {
+            break;
+        }
+        if start.elapsed() >= DRAIN_TIMEOUT {
+            config.logger.warn(&format!(
+                "Stopping with {} commands still running after {:?}",
+                count, DRAIN_TIMEOUT
+            ));
+            break;
+        }
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+
+    shard_manager.shutdown_all().await;
+}
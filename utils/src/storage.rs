@@ -0,0 +1,335 @@
+//! Persistent storage backed by an embedded SQLite database.
+//!
+//! The [`Storage`] is part of the shared [`Data`](crate::shared_types::Data) and can be accessed
+//! in commands through `ctx.data().storage`. The schema is kept up to date by the migrations in
+//! [`MIGRATIONS`], which are applied when the database is opened.
+
+use poise::serenity_prelude::GuildId;
+use rusqlite::{Connection, OptionalExtension, params};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+/// The schema migrations, applied in order. The number of applied migrations is stored in the
+/// `user_version` pragma of the database. Never edit or remove a migration, add a new one instead.
+pub const MIGRATIONS: &[&str] = &[
+    // 1: Key-value store
+    "CREATE TABLE kv (
+        scope INTEGER NOT NULL,
+        key TEXT NOT NULL,
+        value TEXT NOT NULL,
+        updated_at INTEGER NOT NULL,
+        PRIMARY KEY (scope, key)
+    );",
+];
+
+/// Errors that can occur while accessing the storage.
+#[derive(Debug)]
+pub enum StorageError {
+    /// The database directory could not be created.
+    Io(std::io::Error),
+    /// A database operation failed.
+    Sqlite(rusqlite::Error),
+    /// A stored value could not be serialized or deserialized.
+    Serde(serde_json::Error),
+    /// The database was created by a newer version of the bot.
+    UnknownVersion(usize),
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(e) => write!(f, "Could not create database directory: {}", e),
+            Self::Sqlite(e) => write!(f, "Database error: {}", e),
+            Self::Serde(e) => write!(f, "Invalid stored value: {}", e),
+            Self::UnknownVersion(version) => write!(
+                f,
+                "Database schema version {} is newer than the supported version {}",
+                version,
+                MIGRATIONS.len()
+            ),
+        }
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+impl From<rusqlite::Error> for StorageError {
+    fn from(e: rusqlite::Error) -> Self {
+        Self::Sqlite(e)
+    }
+}
+
+impl From<serde_json::Error> for StorageError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Serde(e)
+    }
+}
+
+/// Handle to the SQLite database.
+///
+/// All operations are blocking, but only hold the connection for the duration of a single query.
+#[derive(Debug)]
+pub struct Storage {
+    connection: Mutex<Connection>,
+}
+
+impl Storage {
+    /// Opens or creates the database at the given path and applies pending migrations. Missing
+    /// parent directories are created.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StorageError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent()
+            && !parent.as_os_str().is_empty()
+        {
+            std::fs::create_dir_all(parent).map_err(StorageError::Io)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    /// Opens a new empty in-memory database with all migrations applied. Useful for tests.
+    pub fn open_in_memory() -> Result<Self, StorageError> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut connection: Connection) -> Result<Self, StorageError> {
+        migrate(&mut connection)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    /// Runs `f` with exclusive access to the connection. Prefer the typed repositories, like
+    /// [`Storage::kv`], over raw queries.
+    pub fn with_connection<T>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<T, StorageError>,
+    ) -> Result<T, StorageError> {
+        let connection = self
+            .connection
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        f(&connection)
+    }
+
+    /// Returns the number of applied migrations.
+    pub fn schema_version(&self) -> Result<usize, StorageError> {
+        self.with_connection(|connection| Ok(user_version(connection)?))
+    }
+
+    /// Returns the key-value repository.
+    pub fn kv(&self) -> KeyValueRepository<'_> {
+        KeyValueRepository { storage: self }
+    }
+}
+
+fn user_version(connection: &Connection) -> rusqlite::Result<usize> {
+    connection.pragma_query_value(None, "user_version", |row| row.get(0))
+}
+
+fn migrate(connection: &mut Connection) -> Result<(), StorageError> {
+    let version = user_version(connection)?;
+    if version > MIGRATIONS.len() {
+        return Err(StorageError::UnknownVersion(version));
+    }
+
+    for (i, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let transaction = connection.transaction()?;
+        transaction.execute_batch(migration)?;
+        transaction.pragma_update(None, "user_version", i + 1)?;
+        transaction.commit()?;
+    }
+
+    Ok(())
+}
+
+/// The scope a stored value belongs to.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Scope {
+    /// Shared by all guilds
+    Global,
+    /// Belongs to a single guild
+    Guild(GuildId),
+}
+
+impl Scope {
+    fn id(self) -> i64 {
+        match self {
+            Self::Global => 0,
+            // Snowflakes fit into 63 bits, so this does not wrap
+            Self::Guild(guild_id) => guild_id.get() as i64,
+        }
+    }
+}
+
+/// Stores arbitrary serializable values by scope and key. Values are stored as JSON.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyValueRepository<'a> {
+    storage: &'a Storage,
+}
+
+impl KeyValueRepository<'_> {
+    /// Returns the value stored under the given key, if any.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        scope: Scope,
+        key: &str,
+    ) -> Result<Option<T>, StorageError> {
+        let value: Option<String> = self.storage.with_connection(|connection| {
+            Ok(connection
+                .query_row(
+                    "SELECT value FROM kv WHERE scope = ?1 AND key = ?2",
+                    params![scope.id(), key],
+                    |row| row.get(0),
+                )
+                .optional()?)
+        })?;
+
+        Ok(value
+            .map(|value| serde_json::from_str(&value))
+            .transpose()?)
+    }
+
+    /// Stores the value under the given key, replacing the previous value.
+    pub fn set<T: Serialize>(
+        &self,
+        scope: Scope,
+        key: &str,
+        value: &T,
+    ) -> Result<(), StorageError> {
+        let value = serde_json::to_string(value)?;
+        self.storage.with_connection(|connection| {
+            connection.execute(
+                "INSERT INTO kv (scope, key, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+                ON CONFLICT (scope, key) DO UPDATE SET value = ?3, updated_at = ?4",
+                params![scope.id(), key, value, chrono::Utc::now().timestamp()],
+            )?;
+            Ok(())
+        })
+    }
+
+    /// Removes the value stored under the given key. Returns whether a value was removed.
+    pub fn remove(&self, scope: Scope, key: &str) -> Result<bool, StorageError> {
+        self.storage.with_connection(|connection| {
+            let removed = connection.execute(
+                "DELETE FROM kv WHERE scope = ?1 AND key = ?2",
+                params![scope.id(), key],
+            )?;
+            Ok(removed > 0)
+        })
+    }
+
+    /// Returns all keys in the scope that start with `prefix`, in ascending order.
+    pub fn keys(&self, scope: Scope, prefix: &str) -> Result<Vec<String>, StorageError> {
+        self.storage.with_connection(|connection| {
+            let mut statement = connection.prepare(
+                "SELECT key FROM kv WHERE scope = ?1 AND substr(key, 1, length(?2)) = ?2
+                ORDER BY key",
+            )?;
+            let keys = statement
+                .query_map(params![scope.id(), prefix], |row| row.get(0))?
+                .collect::<Result<Vec<String>, _>>()?;
+            Ok(keys)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn migrations_set_the_user_version() {
+        let storage = Storage::open_in_memory().unwrap();
+        assert_eq!(storage.schema_version().unwrap(), MIGRATIONS.len());
+    }
+
+    #[test]
+    fn reopening_does_not_run_migrations_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("data").join("bot.db");
+
+        let storage = Storage::open(&path).unwrap();
+        storage.kv().set(Scope::Global, "key", &1).unwrap();
+        drop(storage);
+
+        // Running the first migration again would fail, as the table already exists
+        let storage = Storage::open(&path).unwrap();
+        assert_eq!(storage.schema_version().unwrap(), MIGRATIONS.len());
+        assert_eq!(storage.kv().get(Scope::Global, "key").unwrap(), Some(1));
+    }
+
+    #[test]
+    fn newer_schema_versions_are_rejected() {
+        let connection = Connection::open_in_memory().unwrap();
+        connection
+            .pragma_update(None, "user_version", MIGRATIONS.len() + 1)
+            .unwrap();
+
+        match Storage::from_connection(connection) {
+            Err(StorageError::UnknownVersion(version)) => {
+                assert_eq!(version, MIGRATIONS.len() + 1)
+            }
+            result => panic!("expected an unknown version error, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn kv_get_set_overwrite_and_remove() {
+        let storage = Storage::open_in_memory().unwrap();
+        let kv = storage.kv();
+
+        assert_eq!(kv.get::<String>(Scope::Global, "key").unwrap(), None);
+        kv.set(Scope::Global, "key", &"first").unwrap();
+        assert_eq!(
+            kv.get::<String>(Scope::Global, "key").unwrap().as_deref(),
+            Some("first")
+        );
+        kv.set(Scope::Global, "key", &"second").unwrap();
+        assert_eq!(
+            kv.get::<String>(Scope::Global, "key").unwrap().as_deref(),
+            Some("second")
+        );
+
+        assert!(kv.remove(Scope::Global, "key").unwrap());
+        assert!(!kv.remove(Scope::Global, "key").unwrap());
+        assert_eq!(kv.get::<String>(Scope::Global, "key").unwrap(), None);
+    }
+
+    #[test]
+    fn kv_keys_match_the_prefix_in_order() {
+        let storage = Storage::open_in_memory().unwrap();
+        let kv = storage.kv();
+        for key in ["warn:2", "warn:1", "other", "warning"] {
+            kv.set(Scope::Global, key, &true).unwrap();
+        }
+
+        assert_eq!(
+            kv.keys(Scope::Global, "warn:").unwrap(),
+            vec!["warn:1", "warn:2"]
+        );
+        assert_eq!(kv.keys(Scope::Global, "").unwrap().len(), 4);
+    }
+
+    #[test]
+    fn kv_scopes_are_isolated() {
+        let storage = Storage::open_in_memory().unwrap();
+        let kv = storage.kv();
+        let first = Scope::Guild(GuildId::new(1));
+        let second = Scope::Guild(GuildId::new(2));
+
+        kv.set(Scope::Global, "key", &0).unwrap();
+        kv.set(first, "key", &1).unwrap();
+
+        assert_eq!(kv.get(Scope::Global, "key").unwrap(), Some(0));
+        assert_eq!(kv.get(first, "key").unwrap(), Some(1));
+        assert_eq!(kv.get::<i32>(second, "key").unwrap(), None);
+        assert!(kv.keys(second, "").unwrap().is_empty());
+
+        assert!(!kv.remove(second, "key").unwrap());
+        assert!(kv.remove(first, "key").unwrap());
+        assert_eq!(kv.get(Scope::Global, "key").unwrap(), Some(0));
+    }
+}